@@ -0,0 +1,369 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::responses::ResponseField;
+
+pub const DEFAULT_NP_COMMAND: &str = "!np";
+pub const DEFAULT_NP_FORMAT: &str =
+    "{artist} - {title} [{diff}] ({creator}) {mods} | {status} {link}";
+pub const DEFAULT_PP_COMMAND: &str = "!pp";
+pub const DEFAULT_PP_FORMAT: &str =
+    "95%: {pp_95}pp | 97%: {pp_97}pp | 98%: {pp_98}pp | 99%: {pp_99}pp | 100%: {pp_100}pp {mods}";
+
+/// Chat roles ordered by privilege, so a role satisfies every requirement below it.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatRole {
+    #[default]
+    Everyone,
+    Subscriber,
+    Vip,
+    Moderator,
+    Broadcaster,
+}
+
+impl ChatRole {
+    pub const ALL: [ChatRole; 5] = [
+        ChatRole::Everyone,
+        ChatRole::Subscriber,
+        ChatRole::Vip,
+        ChatRole::Moderator,
+        ChatRole::Broadcaster,
+    ];
+
+    pub fn from_badge(set_id: &str) -> Option<Self> {
+        match set_id {
+            "broadcaster" => Some(ChatRole::Broadcaster),
+            "moderator" | "lead_moderator" => Some(ChatRole::Moderator),
+            "vip" => Some(ChatRole::Vip),
+            "subscriber" | "founder" => Some(ChatRole::Subscriber),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ChatRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChatRole::Everyone => write!(f, "Everyone"),
+            ChatRole::Subscriber => write!(f, "Subscribers"),
+            ChatRole::Vip => write!(f, "VIPs"),
+            ChatRole::Moderator => write!(f, "Moderators"),
+            ChatRole::Broadcaster => write!(f, "Broadcaster"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryMode {
+    #[default]
+    Reply,
+    Message,
+    Announcement,
+    Whisper,
+}
+
+impl DeliveryMode {
+    pub const ALL: [DeliveryMode; 4] = [
+        DeliveryMode::Reply,
+        DeliveryMode::Message,
+        DeliveryMode::Announcement,
+        DeliveryMode::Whisper,
+    ];
+}
+
+impl Display for DeliveryMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeliveryMode::Reply => write!(f, "Reply"),
+            DeliveryMode::Message => write!(f, "Chat message"),
+            DeliveryMode::Announcement => write!(f, "Announcement"),
+            DeliveryMode::Whisper => write!(f, "Whisper"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnouncementColor {
+    #[default]
+    Primary,
+    Blue,
+    Green,
+    Orange,
+    Purple,
+}
+
+impl AnnouncementColor {
+    pub const ALL: [AnnouncementColor; 5] = [
+        AnnouncementColor::Primary,
+        AnnouncementColor::Blue,
+        AnnouncementColor::Green,
+        AnnouncementColor::Orange,
+        AnnouncementColor::Purple,
+    ];
+}
+
+impl Display for AnnouncementColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnnouncementColor::Primary => write!(f, "Channel colour"),
+            AnnouncementColor::Blue => write!(f, "Blue"),
+            AnnouncementColor::Green => write!(f, "Green"),
+            AnnouncementColor::Orange => write!(f, "Orange"),
+            AnnouncementColor::Purple => write!(f, "Purple"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomCommand {
+    pub trigger: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    pub template: String,
+    pub enabled: bool,
+    #[serde(default)]
+    pub min_role: ChatRole,
+    #[serde(default)]
+    pub delivery: DeliveryMode,
+    #[serde(default)]
+    pub announcement_color: AnnouncementColor,
+}
+
+impl CustomCommand {
+    pub fn new(trigger: &str, template: &str) -> Self {
+        Self {
+            trigger: trigger.to_string(),
+            aliases: Vec::new(),
+            template: template.to_string(),
+            enabled: true,
+            min_role: ChatRole::Everyone,
+            delivery: DeliveryMode::default(),
+            announcement_color: AnnouncementColor::default(),
+        }
+    }
+
+    pub fn triggers(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.trigger.as_str())
+            .chain(self.aliases.iter().map(String::as_str))
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+    }
+}
+
+pub const DEFAULT_IGNORED_USERS: &[&str] = &[
+    "nightbot",
+    "streamelements",
+    "streamlabs",
+    "moobot",
+    "fossabot",
+];
+
+pub fn default_ignored_users() -> Vec<String> {
+    DEFAULT_IGNORED_USERS
+        .iter()
+        .map(|u| u.to_string())
+        .collect()
+}
+
+pub fn is_ignored_user(ignored_users: &[String], login: &str, user_id: &str) -> bool {
+    ignored_users.iter().any(|entry| {
+        let entry = entry.trim().trim_start_matches('@');
+        entry.eq_ignore_ascii_case(login) || entry == user_id
+    })
+}
+
+pub fn default_commands() -> Vec<CustomCommand> {
+    vec![
+        CustomCommand::new(DEFAULT_NP_COMMAND, DEFAULT_NP_FORMAT),
+        CustomCommand::new(DEFAULT_PP_COMMAND, DEFAULT_PP_FORMAT),
+    ]
+}
+
+// appended by some chat clients to get around Twitch's duplicate message check
+const INVISIBLE_SUFFIX: &str = "\u{e0000}";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation<'a> {
+    pub trigger: &'a str,
+    pub args: Vec<&'a str>,
+    /// Everything after the trigger, with its spacing kept.
+    pub rest: &'a str,
+}
+
+impl<'a> Invocation<'a> {
+    pub fn parse(text: &'a str) -> Option<Self> {
+        let mut tokens = text
+            .split_whitespace()
+            .filter(|token| *token != INVISIBLE_SUFFIX);
+        let trigger = tokens.next()?;
+        let rest = &text[text.find(trigger).unwrap_or(0) + trigger.len()..];
+        Some(Self {
+            trigger,
+            args: tokens.collect(),
+            rest: rest.trim().trim_end_matches(INVISIBLE_SUFFIX).trim_end(),
+        })
+    }
+}
+
+pub fn find_command<'a>(
+    commands: &'a [CustomCommand],
+    invocation: &Invocation,
+) -> Option<&'a CustomCommand> {
+    let invoked = invocation.trigger.to_lowercase();
+    commands.iter().filter(|c| c.enabled).find(|c| {
+        c.triggers()
+            .any(|trigger| trigger.to_lowercase() == invoked)
+    })
+}
+
+pub const CONTROL_COMMAND: &str = "!bot";
+pub const SET_FORMAT_PREFIX: &str = "!set";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlCommand {
+    Enable(bool),
+    Cooldown(u64),
+    SetFormat {
+        trigger: String,
+        template: String,
+    },
+    Usage {
+        field: ResponseField,
+        trigger: String,
+    },
+}
+
+impl ControlCommand {
+    pub fn parse(invocation: &Invocation, commands: &[CustomCommand]) -> Option<Self> {
+        let trigger = invocation.trigger.to_lowercase();
+
+        if trigger == CONTROL_COMMAND {
+            let parsed = match invocation.args.as_slice() {
+                [arg] if arg.eq_ignore_ascii_case("on") => Some(ControlCommand::Enable(true)),
+                [arg] if arg.eq_ignore_ascii_case("off") => Some(ControlCommand::Enable(false)),
+                [arg, seconds] if arg.eq_ignore_ascii_case("cooldown") => {
+                    seconds.parse().ok().map(ControlCommand::Cooldown)
+                }
+                _ => None,
+            };
+            return Some(parsed.unwrap_or_else(|| ControlCommand::Usage {
+                field: ResponseField::ControlUsage,
+                trigger: CONTROL_COMMAND.to_string(),
+            }));
+        }
+
+        let name = trigger
+            .strip_prefix(SET_FORMAT_PREFIX)
+            .filter(|name| !name.is_empty())?;
+        let command = commands.iter().find(|c| {
+            c.triggers()
+                .any(|t| t.trim_start_matches('!').eq_ignore_ascii_case(name))
+        })?;
+
+        if invocation.rest.is_empty() {
+            return Some(ControlCommand::Usage {
+                field: ResponseField::SetFormatUsage,
+                trigger: invocation.trigger.to_string(),
+            });
+        }
+        Some(ControlCommand::SetFormat {
+            trigger: command.trigger.clone(),
+            template: invocation.rest.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_disabled_commands() {
+        let mut command = CustomCommand::new("!np", "{title}");
+        command.enabled = false;
+        let invocation = Invocation::parse("!np").unwrap();
+        assert!(find_command(&[command], &invocation).is_none());
+    }
+
+    #[test]
+    fn matches_triggers_and_aliases_exactly_ignoring_case() {
+        let mut command = CustomCommand::new("!np", "{title}");
+        command.aliases = vec![" !song ".to_string(), String::new()];
+        let commands = [command];
+
+        let find = |text| find_command(&commands, &Invocation::parse(text).unwrap());
+        assert!(find("!NP").is_some());
+        assert!(find("!song now").is_some());
+        assert!(find("!npx").is_none());
+        assert!(find("np").is_none());
+    }
+
+    #[test]
+    fn splits_trigger_and_arguments() {
+        let invocation = Invocation::parse("  !pp 99  2x +HD").unwrap();
+        assert_eq!(invocation.trigger, "!pp");
+        assert_eq!(invocation.args, ["99", "2x", "+HD"]);
+        assert_eq!(Invocation::parse("   "), None);
+    }
+
+    #[test]
+    fn drops_the_invisible_suffix() {
+        let text = format!("!np {}", INVISIBLE_SUFFIX);
+        let invocation = Invocation::parse(&text).unwrap();
+        assert!(invocation.args.is_empty());
+        assert_eq!(invocation.rest, "");
+    }
+
+    #[test]
+    fn ignores_users_by_login_or_id() {
+        let ignored = vec![" @Nightbot ".to_string(), "12345".to_string()];
+        assert!(is_ignored_user(&ignored, "nightbot", "1"));
+        assert!(is_ignored_user(&ignored, "someone", "12345"));
+        assert!(!is_ignored_user(&ignored, "someone", "123"));
+    }
+
+    #[test]
+    fn parses_control_commands() {
+        let commands = default_commands();
+        let parse = |text| ControlCommand::parse(&Invocation::parse(text).unwrap(), &commands);
+
+        assert_eq!(parse("!BOT off"), Some(ControlCommand::Enable(false)));
+        assert_eq!(parse("!bot cooldown 5"), Some(ControlCommand::Cooldown(5)));
+        assert!(matches!(
+            parse("!bot cooldown soon"),
+            Some(ControlCommand::Usage {
+                field: ResponseField::ControlUsage,
+                ..
+            })
+        ));
+        assert_eq!(
+            parse("!setnp {title} {link}"),
+            Some(ControlCommand::SetFormat {
+                trigger: DEFAULT_NP_COMMAND.to_string(),
+                template: "{title} {link}".to_string(),
+            })
+        );
+        assert_eq!(
+            parse("!setnp {artist}  -  {title}"),
+            Some(ControlCommand::SetFormat {
+                trigger: DEFAULT_NP_COMMAND.to_string(),
+                template: "{artist}  -  {title}".to_string(),
+            })
+        );
+        assert!(matches!(
+            parse("!setnp"),
+            Some(ControlCommand::Usage {
+                field: ResponseField::SetFormatUsage,
+                ..
+            })
+        ));
+        assert_eq!(parse("!setup"), None);
+        assert_eq!(parse("!np"), None);
+    }
+}
@@ -2,8 +2,10 @@ use keyring::Entry;
 use thiserror::Error;
 
 use super::APP_NAME;
+use crate::twitch::endpoints::endpoints;
 
 const TOKEN_KEY: &str = "twitch-access-token";
+const REFRESH_TOKEN_KEY: &str = "twitch-refresh-token";
 
 #[derive(Debug, Error)]
 pub enum CredentialError {
@@ -16,31 +18,64 @@ pub enum CredentialError {
 pub struct CredentialStore;
 
 impl CredentialStore {
-    fn entry() -> Result<Entry, CredentialError> {
-        Ok(Entry::new(APP_NAME, TOKEN_KEY)?)
+    // tokens only work with their client id, so e.g. the mock gets its own keyring service
+    fn entry(key: &str) -> Result<Entry, CredentialError> {
+        let endpoints = endpoints();
+        if endpoints.uses_default_client_id() {
+            Ok(Entry::new(APP_NAME, key)?)
+        } else {
+            Ok(Entry::new(
+                &format!("{}-{}", APP_NAME, endpoints.client_id),
+                key,
+            )?)
+        }
     }
 
-    pub fn save_token(token: &str) -> Result<(), CredentialError> {
-        let entry = Self::entry()?;
-        entry.set_password(token)?;
+    fn save(key: &str, value: &str) -> Result<(), CredentialError> {
+        let entry = Self::entry(key)?;
+        entry.set_password(value)?;
         Ok(())
     }
 
-    pub fn load_token() -> Result<String, CredentialError> {
-        let entry = Self::entry()?;
+    fn load(key: &str) -> Result<String, CredentialError> {
+        let entry = Self::entry(key)?;
         match entry.get_password() {
-            Ok(token) => Ok(token),
+            Ok(value) => Ok(value),
             Err(keyring::Error::NoEntry) => Err(CredentialError::NotFound),
             Err(e) => Err(CredentialError::Keyring(e)),
         }
     }
 
-    pub fn delete_token() -> Result<(), CredentialError> {
-        let entry = Self::entry()?;
+    fn delete(key: &str) -> Result<(), CredentialError> {
+        let entry = Self::entry(key)?;
         match entry.delete_credential() {
             Ok(()) => Ok(()),
             Err(keyring::Error::NoEntry) => Ok(()),
             Err(e) => Err(CredentialError::Keyring(e)),
         }
     }
+
+    pub fn save_token(token: &str) -> Result<(), CredentialError> {
+        Self::save(TOKEN_KEY, token)
+    }
+
+    pub fn load_token() -> Result<String, CredentialError> {
+        Self::load(TOKEN_KEY)
+    }
+
+    pub fn delete_token() -> Result<(), CredentialError> {
+        Self::delete(TOKEN_KEY)
+    }
+
+    pub fn save_refresh_token(token: &str) -> Result<(), CredentialError> {
+        Self::save(REFRESH_TOKEN_KEY, token)
+    }
+
+    pub fn load_refresh_token() -> Result<String, CredentialError> {
+        Self::load(REFRESH_TOKEN_KEY)
+    }
+
+    pub fn delete_refresh_token() -> Result<(), CredentialError> {
+        Self::delete(REFRESH_TOKEN_KEY)
+    }
 }
@@ -3,8 +3,8 @@ use std::sync::{Arc, Mutex};
 use iced::Alignment::Center;
 use iced::futures::channel::mpsc;
 use iced::widget::{
-    button, center_x, center_y, checkbox, column, container, rich_text, row, scrollable, span,
-    text, text_input,
+    button, center_x, center_y, checkbox, column, container, pick_list, rich_text, row, scrollable,
+    span, text, text_input,
 };
 use iced::{Element, Fill, Font};
 
@@ -13,16 +13,24 @@ use super::components::{
     tab_button_active,
 };
 use super::theme::{ColorPalette, get_current_theme, palette};
+use crate::commands::{AnnouncementColor, ChatRole, CustomCommand, DeliveryMode, default_commands};
 use crate::credentials::CredentialStore;
 use crate::logging::{LogEntry, LogLevel};
-use crate::osu::core::{BeatmapData, MemoryEvent, OsuCommand, OsuStatus};
+use crate::osu::core::{BeatmapData, BeatmapStatus, MemoryEvent, OsuCommand, OsuStatus};
 use crate::osu::pp::get_pp_spread;
-use crate::placeholders::Placeholders;
+use crate::placeholders::{PLACEHOLDERS, Placeholders};
 use crate::preferences::PreferencesStore;
-use crate::twitch::{
-    DEFAULT_NP_COMMAND, DEFAULT_NP_FORMAT, DEFAULT_PP_COMMAND, DEFAULT_PP_FORMAT, TwitchCommand,
-    TwitchEvent, TwitchStatus,
+use crate::requests::{self, BeatmapRequest, RequestSource};
+use crate::responses::{Language, ResponseField, Responses};
+use crate::twitch::announce::AnnounceSettings;
+use crate::twitch::auth::{TokenInfo, delivery_scope, format_expiry};
+use crate::twitch::cooldown::CooldownSettings;
+use crate::twitch::core::{
+    ChatbotSettings, CustomReward, PreferencesUpdate, TwitchCommand, TwitchEvent, TwitchStatus,
 };
+use crate::twitch::dispatch::TestMessage;
+use crate::twitch::message::LongMessageMode;
+use crate::twitch::shared_chat::{SharedChatMode, SharedChatSettings};
 use crate::{
     VERSION, get_osu_channel, get_twitch_channel, log_debug, log_error, log_info, log_warn,
 };
@@ -33,34 +41,93 @@ pub type CommandReceiver<T> = Arc<Mutex<Option<mpsc::Receiver<T>>>>;
 pub enum Tab {
     Main,
     Settings,
+    Requests,
+    TestChat,
     Data,
     Console,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LoginState {
+    Idle,
+    Failed(String),
+    Requesting,
+    Pending {
+        user_code: String,
+        verification_uri: String,
+    },
+}
+
 #[derive(Debug, Clone)]
 pub enum Message {
     TabSelected(Tab),
     TokenInputChanged(String),
     AutoConnectToggled(bool),
+    BotEnabledToggled(bool),
+    ChannelLoginChanged(String),
     TokenHelpClicked,
     ConnectClicked,
     DisconnectClicked,
     ClearTokenClicked,
-    NpCommandChanged(String),
-    NpFormatChanged(String),
-    ResetNpCommand,
-    ResetNpFormat,
-    PpCommandChanged(String),
-    PpFormatChanged(String),
-    ResetPpCommand,
-    ResetPpFormat,
+    LoginClicked,
+    CancelLoginClicked,
+    CommandTriggerChanged(usize, String),
+    CommandAliasesChanged(usize, String),
+    CommandTemplateChanged(usize, String),
+    CommandToggled(usize, bool),
+    CommandRoleChanged(usize, ChatRole),
+    CommandDeliveryChanged(usize, DeliveryMode),
+    CommandAnnouncementColorChanged(usize, AnnouncementColor),
+    AddCommand,
+    RemoveCommand(usize),
+    ResetCommands,
+    GlobalCooldownChanged(String),
+    UserCooldownChanged(String),
+    CooldownBypassToggled(bool),
+    CooldownReplyToggled(bool),
+    AnnounceToggled(bool),
+    AnnounceDelayChanged(String),
+    AnnounceIntervalChanged(String),
+    AnnounceTemplateChanged(String),
+    LongMessagesSelected(LongMessageMode),
+    IgnoredUsersChanged(String),
+    SharedChatModeSelected(SharedChatMode),
+    SharedChatChannelsChanged(String),
+    ResponseLanguageSelected(Language),
+    ResponseChanged(ResponseField, String),
+    StatusNameChanged(BeatmapStatus, String),
+    AcceptRequestsToggled(bool),
+    RequestRoleSelected(ChatRole),
+    ApproveRequest(u64),
+    SkipRequest(u64),
+    RequestRewardSelected(CustomReward),
+    CreateRequestReward,
+    ClearRequestReward,
     OsuEvent(MemoryEvent),
     TwitchEvent(TwitchEvent),
     LogEvent(LogEntry),
     LinkClicked(String),
+    TestChatLoginChanged(String),
+    TestChatRoleToggled(ChatRole, bool),
+    TestChatInputChanged(String),
+    SendTestChat,
+    ClearTestChat,
 }
 
 const MAX_LOG_ENTRIES: usize = 500;
+const DEFAULT_TEST_LOGIN: &str = "viewer";
+// badges that can be given to the test chat's user
+const TEST_CHAT_ROLES: [ChatRole; 4] = [
+    ChatRole::Broadcaster,
+    ChatRole::Moderator,
+    ChatRole::Vip,
+    ChatRole::Subscriber,
+];
+
+struct TestChatLine {
+    sender: Option<String>,
+    text: String,
+}
 
 #[allow(dead_code)]
 pub struct State {
@@ -68,19 +135,35 @@ pub struct State {
     token_input_value: String,
     token_saved: bool,
     auto_connect_value: bool,
-    np_command: String,
-    np_format: String,
-    pp_command: String,
-    pp_format: String,
+    channel_login: String,
+    bot_enabled: bool,
+    commands: Vec<CustomCommand>,
+    cooldowns: CooldownSettings,
+    announcements: AnnounceSettings,
+    long_messages: LongMessageMode,
+    ignored_users: Vec<String>,
+    shared_chat: SharedChatSettings,
+    responses: Responses,
+    accept_requests: bool,
+    request_min_role: ChatRole,
+    requests: Vec<BeatmapRequest>,
+    request_reward_id: String,
+    rewards: Vec<CustomReward>,
     current_beatmap: Option<BeatmapData>,
     cached_pp: Option<crate::osu::pp::PpValues>,
     osu_status: OsuStatus,
     osu_cmd_tx: mpsc::Sender<OsuCommand>,
     pub osu_cmd_rx: CommandReceiver<OsuCommand>,
     twitch_status: TwitchStatus,
+    token_info: Option<TokenInfo>,
+    login_state: LoginState,
     twitch_cmd_tx: mpsc::Sender<TwitchCommand>,
     pub twitch_cmd_rx: CommandReceiver<TwitchCommand>,
     log_entries: Vec<LogEntry>,
+    test_chat_login: String,
+    test_chat_roles: Vec<ChatRole>,
+    test_chat_input: String,
+    test_chat_lines: Vec<TestChatLine>,
 }
 
 impl State {
@@ -94,58 +177,97 @@ impl State {
             (tx.clone(), Arc::clone(rx))
         };
 
-        let (token_input_value, token_saved) = match CredentialStore::load_token() {
+        // the stored token is never copied into the input, since it may be refreshed
+        // behind the GUI's back
+        let saved_token = match CredentialStore::load_token() {
             Ok(token) => {
                 log_debug!("gui", "Loaded saved token from credential store");
-                (token, true)
+                Some(token)
             }
             Err(e) => {
                 log_debug!("gui", "No saved token found: {}", e);
-                (String::new(), false)
+                None
             }
         };
+        let token_saved = saved_token.is_some();
 
         let prefs = PreferencesStore::load_or_default();
-        let (auto_connect_value, np_command, np_format, pp_command, pp_format) = (
-            prefs.auto_connect(),
-            prefs.np_command().to_string(),
-            prefs.np_format().to_string(),
-            prefs.pp_command().to_string(),
-            prefs.pp_format().to_string(),
-        );
-
-        let twitch_status = if auto_connect_value && token_saved {
-            log_info!("gui", "Auto-connecting to Twitch...");
-            let _ = twitch_cmd_tx.clone().try_send(TwitchCommand::Connect {
-                token: token_input_value.clone(),
-                np_command: np_command.clone(),
-                np_format: np_format.clone(),
-                pp_command: pp_command.clone(),
-                pp_format: pp_format.clone(),
-            });
-            TwitchStatus::Connecting
-        } else {
-            TwitchStatus::default()
-        };
-
-        Self {
+        let auto_connect_value = prefs.auto_connect();
+        let channel_login = prefs.channel_login().to_string();
+        let bot_enabled = prefs.bot_enabled();
+        let commands = prefs.commands().to_vec();
+        let cooldowns = prefs.cooldowns().clone();
+        let announcements = prefs.announcements().clone();
+        let long_messages = prefs.long_messages();
+        let ignored_users = prefs.ignored_users().to_vec();
+        let shared_chat = prefs.shared_chat().clone();
+        let responses = prefs.responses().clone();
+        let accept_requests = prefs.accept_requests();
+        requests::set_accepting(accept_requests);
+        let request_min_role = prefs.request_min_role();
+        let request_reward_id = prefs.request_reward_id().to_string();
+
+        let mut state = Self {
             active_tab: Tab::Main,
-            token_input_value,
+            token_input_value: String::new(),
             token_saved,
             auto_connect_value,
-            np_command,
-            np_format,
-            pp_command,
-            pp_format,
+            channel_login,
+            bot_enabled,
+            commands,
+            cooldowns,
+            announcements,
+            long_messages,
+            ignored_users,
+            shared_chat,
+            responses,
+            accept_requests,
+            request_min_role,
+            requests: Vec::new(),
+            request_reward_id,
+            rewards: Vec::new(),
             current_beatmap: None,
             cached_pp: None,
             osu_status: OsuStatus::default(),
             osu_cmd_tx,
             osu_cmd_rx,
-            twitch_status,
+            twitch_status: TwitchStatus::default(),
+            token_info: None,
+            login_state: LoginState::Idle,
             twitch_cmd_tx,
             twitch_cmd_rx,
             log_entries: Vec::new(),
+            test_chat_login: DEFAULT_TEST_LOGIN.to_string(),
+            test_chat_roles: Vec::new(),
+            test_chat_input: String::new(),
+            test_chat_lines: Vec::new(),
+        };
+
+        if auto_connect_value && let Some(token) = saved_token {
+            log_info!("gui", "Auto-connecting to Twitch...");
+            let _ = state.twitch_cmd_tx.try_send(TwitchCommand::Connect {
+                token,
+                settings: state.chatbot_settings(),
+                channel_login: state.channel_login.clone(),
+            });
+            state.twitch_status = TwitchStatus::Connecting;
+        }
+
+        state
+    }
+
+    fn chatbot_settings(&self) -> ChatbotSettings {
+        ChatbotSettings {
+            enabled: self.bot_enabled,
+            commands: self.commands.clone(),
+            cooldowns: self.cooldowns.clone(),
+            request_min_role: self.request_min_role,
+            request_reward_id: self.request_reward_id.clone(),
+            announcements: self.announcements.clone(),
+            long_messages: self.long_messages,
+            ignored_users: self.ignored_users.clone(),
+            shared_chat: self.shared_chat.clone(),
+            responses: self.responses.clone(),
         }
     }
 
@@ -172,6 +294,20 @@ impl State {
                     tab_button
                 })
                 .on_press(Message::TabSelected(Tab::Settings)),
+            button(text("Requests").size(12))
+                .style(if self.active_tab == Tab::Requests {
+                    tab_button_active
+                } else {
+                    tab_button
+                })
+                .on_press(Message::TabSelected(Tab::Requests)),
+            button(text("Test Chat").size(12))
+                .style(if self.active_tab == Tab::TestChat {
+                    tab_button_active
+                } else {
+                    tab_button
+                })
+                .on_press(Message::TabSelected(Tab::TestChat)),
             button(text("Data").size(12))
                 .style(if self.active_tab == Tab::Data {
                     tab_button_active
@@ -200,6 +336,8 @@ impl State {
         let content = match self.active_tab {
             Tab::Main => self.view_main_tab(&p),
             Tab::Settings => self.view_settings_tab(&p),
+            Tab::Requests => self.view_requests_tab(&p),
+            Tab::TestChat => self.view_test_chat_tab(&p),
             Tab::Data => self.view_data_tab(&p),
             Tab::Console => self.view_console_tab(&p),
         };
@@ -238,9 +376,11 @@ impl State {
             .on_input(Message::TokenInputChanged);
 
         let action_button = match &self.twitch_status {
-            TwitchStatus::Connected(_) => button(text("Disconnect").size(14))
-                .style(primary_button)
-                .on_press(Message::DisconnectClicked),
+            TwitchStatus::Connected(_) | TwitchStatus::Reconnecting(_) => {
+                button(text("Disconnect").size(14))
+                    .style(primary_button)
+                    .on_press(Message::DisconnectClicked)
+            }
             TwitchStatus::Connecting => {
                 button(text("Connecting...").size(14)).style(primary_button)
             }
@@ -265,15 +405,105 @@ impl State {
             main_row = main_row.push(clear_btn);
         }
 
+        // the channel is only read when connecting
+        let mut channel_input = text_input("Your own channel", &self.channel_login)
+            .size(12)
+            .style(primary_text_input);
+        if matches!(
+            self.twitch_status,
+            TwitchStatus::Disconnected | TwitchStatus::Error(_)
+        ) {
+            channel_input = channel_input.on_input(Message::ChannelLoginChanged);
+        }
+        let channel_row = row![text("Channel").size(14), channel_input]
+            .spacing(10)
+            .align_y(Center);
+
         let auto_connect_checkbox = checkbox(self.auto_connect_value)
             .label("Auto-connect on startup")
             .on_toggle(Message::AutoConnectToggled)
             .size(14)
             .text_size(12);
 
-        let main_content = column![main_row, auto_connect_checkbox]
+        let login_row: Element<'_, Message> = match &self.login_state {
+            LoginState::Idle | LoginState::Failed(_) => {
+                let mut btn = button(text("Log in with Twitch").size(12)).style(primary_button);
+                if !matches!(self.twitch_status, TwitchStatus::Connected(_)) {
+                    btn = btn.on_press(Message::LoginClicked);
+                }
+
+                let mut login_row = row![btn].spacing(10).align_y(Center);
+                if let LoginState::Failed(e) = &self.login_state {
+                    login_row = login_row.push(text(e).size(11).color(p.status_error));
+                }
+                login_row.into()
+            }
+            LoginState::Requesting => button(text("Requesting code...").size(12))
+                .style(primary_button)
+                .into(),
+            LoginState::Pending {
+                user_code,
+                verification_uri,
+            } => row![
+                rich_text![
+                    span::<String, Font>("Enter code "),
+                    span::<String, Font>(user_code.clone()).font(BOLD_FONT),
+                    span::<String, Font>(" at "),
+                    span::<String, Font>("twitch.tv/activate")
+                        .color(p.accent)
+                        .underline(true)
+                        .link(verification_uri.clone()),
+                ]
+                .size(12)
+                .on_link_click(Message::LinkClicked),
+                button(text("Cancel").size(12))
+                    .style(primary_button)
+                    .on_press(Message::CancelLoginClicked),
+            ]
             .spacing(10)
-            .padding(10);
+            .align_y(Center)
+            .into(),
+        };
+
+        let bot_enabled_checkbox = checkbox(self.bot_enabled)
+            .label("Respond in chat")
+            .on_toggle(Message::BotEnabledToggled)
+            .size(14)
+            .text_size(12);
+
+        let mut main_content = column![
+            main_row,
+            channel_row,
+            login_row,
+            auto_connect_checkbox,
+            bot_enabled_checkbox
+        ]
+        .spacing(10)
+        .padding(10);
+
+        if let Some(info) = &self.token_info {
+            let missing_scopes = info.missing_scopes();
+            if !missing_scopes.is_empty() {
+                main_content = main_content.push(
+                    text(format!(
+                        "Token is missing scopes: {} - log in again to grant them",
+                        missing_scopes.join(", ")
+                    ))
+                    .size(11)
+                    .color(p.status_error),
+                );
+            }
+            if info.expires_soon() {
+                main_content = main_content.push(
+                    text(format!(
+                        "Token expires in {}",
+                        format_expiry(info.expires_in)
+                    ))
+                    .size(11)
+                    .color(p.status_warning),
+                );
+            }
+        }
 
         let github_url = "https://github.com/medylme/osu-twitchbot";
 
@@ -310,97 +540,515 @@ impl State {
     }
 
     fn view_settings_tab(&self, p: &ColorPalette) -> Element<'_, Message> {
-        let np_header = text("Now Playing").size(14);
+        let cooldown_header = text("Cooldowns").size(14);
 
-        let np_command_label = text("Command:").size(12);
-        let np_command_input = text_input(DEFAULT_NP_COMMAND, &self.np_command)
+        let global_label = text("Per command (s):").size(12);
+        let global_input = text_input("0", &self.cooldowns.global_seconds.to_string())
             .size(12)
             .width(50)
             .style(primary_text_input)
-            .on_input(Message::NpCommandChanged);
-        let np_command_reset_btn = button(text("Reset").size(12))
-            .style(primary_button)
-            .on_press(Message::ResetNpCommand);
-        let np_command_row = row![np_command_label, np_command_input, np_command_reset_btn]
-            .spacing(10)
-            .align_y(Center);
-
-        let np_format_label = text("Format:").size(12);
-        let np_format_input = text_input(DEFAULT_NP_FORMAT, &self.np_format)
+            .on_input(Message::GlobalCooldownChanged);
+        let user_label = text("Per viewer (s):").size(12);
+        let user_input = text_input("0", &self.cooldowns.user_seconds.to_string())
             .size(12)
-            .width(Fill)
+            .width(50)
             .style(primary_text_input)
-            .on_input(Message::NpFormatChanged);
-        let np_format_reset_btn = button(text("Reset").size(12))
-            .style(primary_button)
-            .on_press(Message::ResetNpFormat);
-        let np_format_row = row![np_format_label, np_format_input, np_format_reset_btn]
+            .on_input(Message::UserCooldownChanged);
+        let cooldown_row = row![global_label, global_input, user_label, user_input]
             .spacing(10)
             .align_y(Center);
 
-        let np_format_help = text("Available placeholders: {artist}, {title}, {diff}, {creator}, {mods}, {link}, {status}")
-            .size(11)
-            .color(p.text_secondary);
-
-        let np_format_preview = self.build_np_format_preview(p);
+        let bypass_checkbox = checkbox(self.cooldowns.privileged_bypass)
+            .label("Broadcaster and moderators ignore cooldowns")
+            .on_toggle(Message::CooldownBypassToggled)
+            .size(14)
+            .text_size(12);
+        let reply_checkbox = checkbox(self.cooldowns.reply_on_cooldown)
+            .label("Reply when a command is on cooldown")
+            .on_toggle(Message::CooldownReplyToggled)
+            .size(14)
+            .text_size(12);
 
-        // PP Command section
-        let pp_header = text("Performance Points").size(14);
+        let ignored_header = text("Ignored Users").size(14);
+        // kept verbatim between the commas so the input round-trips while typing
+        let ignored_input = text_input("nightbot, streamelements", &self.ignored_users.join(","))
+            .size(12)
+            .width(Fill)
+            .style(primary_text_input)
+            .on_input(Message::IgnoredUsersChanged);
+        let ignored_help = text(
+            "Logins or user IDs the bot never answers, separated by commas. Its own messages are always ignored.",
+        )
+        .size(11)
+        .color(p.text_secondary);
+
+        let shared_header = text("Shared Chat").size(14);
+        let shared_mode_row = row![
+            text("Answer messages from:").size(12),
+            pick_list(
+                &SharedChatMode::ALL[..],
+                Some(self.shared_chat.mode),
+                Message::SharedChatModeSelected,
+            )
+            .text_size(12)
+        ]
+        .spacing(10)
+        .align_y(Center);
+        let shared_help = text(
+            "Which channels' chatters the bot answers while you're in a Shared Chat session. Replies are posted in your channel.",
+        )
+        .size(11)
+        .color(p.text_secondary);
+        let mut shared_section = column![shared_mode_row].spacing(10);
+        if self.shared_chat.mode == SharedChatMode::AllowList {
+            // kept verbatim between the commas, like the ignore list
+            let shared_channels_input =
+                text_input("channel1, channel2", &self.shared_chat.channels.join(","))
+                    .size(12)
+                    .width(Fill)
+                    .style(primary_text_input)
+                    .on_input(Message::SharedChatChannelsChanged);
+            shared_section = shared_section.push(shared_channels_input);
+        }
+        let shared_section = shared_section.push(shared_help);
 
-        let pp_command_label = text("Command:").size(12);
-        let pp_command_input = text_input(DEFAULT_PP_COMMAND, &self.pp_command)
+        let announce_header = text("Now Playing Announcements").size(14);
+        let announce_checkbox = checkbox(self.announcements.enabled)
+            .label("Post the map to chat when you start playing it")
+            .on_toggle(Message::AnnounceToggled)
+            .size(14)
+            .text_size(12);
+        let delay_label = text("After playing for (s):").size(12);
+        let delay_input = text_input("0", &self.announcements.delay_seconds.to_string())
             .size(12)
             .width(50)
             .style(primary_text_input)
-            .on_input(Message::PpCommandChanged);
-        let pp_command_reset_btn = button(text("Reset").size(12))
-            .style(primary_button)
-            .on_press(Message::ResetPpCommand);
-        let pp_command_row = row![pp_command_label, pp_command_input, pp_command_reset_btn]
+            .on_input(Message::AnnounceDelayChanged);
+        let interval_label = text("At most every (s):").size(12);
+        let interval_input = text_input("0", &self.announcements.min_interval_seconds.to_string())
+            .size(12)
+            .width(50)
+            .style(primary_text_input)
+            .on_input(Message::AnnounceIntervalChanged);
+        let announce_timing_row = row![delay_label, delay_input, interval_label, interval_input]
             .spacing(10)
             .align_y(Center);
-
-        let pp_format_label = text("Format:").size(12);
-        let pp_format_input = text_input(DEFAULT_PP_FORMAT, &self.pp_format)
+        let announce_format_row = row![
+            text("Format:").size(12),
+            text_input(
+                "Now playing: {artist} - {title}",
+                &self.announcements.template
+            )
             .size(12)
             .width(Fill)
             .style(primary_text_input)
-            .on_input(Message::PpFormatChanged);
-        let pp_format_reset_btn = button(text("Reset").size(12))
+            .on_input(Message::AnnounceTemplateChanged)
+        ]
+        .spacing(10)
+        .align_y(Center);
+        let announce_preview = self.build_template_preview(&self.announcements.template, p);
+
+        let reward_header = text("Channel Points Requests").size(14);
+        let selected_reward = self
+            .rewards
+            .iter()
+            .find(|r| r.id == self.request_reward_id)
+            .cloned();
+        let reward_placeholder = if !self.rewards.is_empty() {
+            "None"
+        } else if self.request_reward_id.is_empty() {
+            "Connect to Twitch to load rewards"
+        } else {
+            "Saved reward (connect to change)"
+        };
+        let reward_picker = pick_list(
+            self.rewards.as_slice(),
+            selected_reward,
+            Message::RequestRewardSelected,
+        )
+        .placeholder(reward_placeholder)
+        .text_size(12);
+        let reward_clear_btn = button(text("Clear").size(12))
             .style(primary_button)
-            .on_press(Message::ResetPpFormat);
-        let pp_format_row = row![pp_format_label, pp_format_input, pp_format_reset_btn]
-            .spacing(10)
-            .align_y(Center);
+            .on_press(Message::ClearRequestReward);
+        let reward_create_btn = button(text("Create").size(12))
+            .style(primary_button)
+            .on_press_maybe(
+                matches!(self.twitch_status, TwitchStatus::Connected(_))
+                    .then_some(Message::CreateRequestReward),
+            );
+        let reward_row = row![
+            text("Reward:").size(12),
+            reward_picker,
+            reward_create_btn,
+            reward_clear_btn
+        ]
+        .spacing(10)
+        .align_y(Center);
+        let reward_help = text(
+            "Redemptions of this reward are queued as beatmap requests. Twitch only lets the bot fulfil and refund rewards created by this app, so only those are listed.",
+        )
+        .size(11)
+        .color(p.text_secondary);
 
-        let pp_format_help =
-            text("Available placeholders: {mods}, {pp_95}, {pp_97}, {pp_98}, {pp_99}, {pp_100}")
-                .size(11)
-                .color(p.text_secondary);
+        let header = text("Commands").size(14);
 
-        let pp_format_preview = self.build_pp_format_preview(p);
+        let help = text(format!(
+            "Available placeholders: {}",
+            PLACEHOLDERS.join(", ")
+        ))
+        .size(11)
+        .color(p.text_secondary);
+
+        let long_messages_row = row![
+            text("Messages over 500 characters:").size(12),
+            pick_list(
+                &LongMessageMode::ALL[..],
+                Some(self.long_messages),
+                Message::LongMessagesSelected,
+            )
+            .text_size(12)
+        ]
+        .spacing(10)
+        .align_y(Center);
 
-        let settings_content = column![
-            np_header,
-            np_command_row,
-            np_format_row,
-            np_format_help,
-            np_format_preview,
+        let mut settings_content = column![
+            cooldown_header,
+            cooldown_row,
+            bypass_checkbox,
+            reply_checkbox,
+            container(text("")).height(15),
+            ignored_header,
+            ignored_input,
+            ignored_help,
+            container(text("")).height(15),
+            shared_header,
+            shared_section,
+            container(text("")).height(15),
+            announce_header,
+            announce_checkbox,
+            announce_timing_row,
+            announce_format_row,
+            announce_preview,
             container(text("")).height(15),
-            pp_header,
-            pp_command_row,
-            pp_format_row,
-            pp_format_help,
-            pp_format_preview
+            reward_header,
+            reward_row,
+            reward_help,
+            container(text("")).height(15),
+            header,
+            help,
+            long_messages_row
         ]
         .spacing(10)
         .padding(10);
 
+        for (index, command) in self.commands.iter().enumerate() {
+            let enabled_checkbox = checkbox(command.enabled)
+                .label("Enabled")
+                .size(14)
+                .text_size(12)
+                .on_toggle(move |value| Message::CommandToggled(index, value));
+
+            let trigger_label = text("Command:").size(12);
+            let trigger_input = text_input("!command", &command.trigger)
+                .size(12)
+                .width(80)
+                .style(primary_text_input)
+                .on_input(move |value| Message::CommandTriggerChanged(index, value));
+            let remove_btn = button(text("Remove").size(12))
+                .style(primary_button)
+                .on_press(Message::RemoveCommand(index));
+            let role_label = text("Allowed:").size(12);
+            let role_picker = pick_list(&ChatRole::ALL[..], Some(command.min_role), move |role| {
+                Message::CommandRoleChanged(index, role)
+            })
+            .text_size(12);
+            let trigger_row = row![trigger_label, trigger_input, enabled_checkbox, remove_btn]
+                .spacing(10)
+                .align_y(Center);
+
+            // kept verbatim between the commas so the input round-trips while typing
+            let aliases_label = text("Aliases:").size(12);
+            let aliases_input = text_input("!song, !nowplaying", &command.aliases.join(","))
+                .size(12)
+                .width(Fill)
+                .style(primary_text_input)
+                .on_input(move |value| Message::CommandAliasesChanged(index, value));
+            let aliases_row = row![aliases_label, aliases_input, role_label, role_picker]
+                .spacing(10)
+                .align_y(Center);
+
+            let template_label = text("Format:").size(12);
+            let template_input = text_input("{artist} - {title}", &command.template)
+                .size(12)
+                .width(Fill)
+                .style(primary_text_input)
+                .on_input(move |value| Message::CommandTemplateChanged(index, value));
+            let template_row = row![template_label, template_input]
+                .spacing(10)
+                .align_y(Center);
+
+            let delivery_label = text("Respond with:").size(12);
+            let delivery_picker = pick_list(
+                &DeliveryMode::ALL[..],
+                Some(command.delivery),
+                move |mode| Message::CommandDeliveryChanged(index, mode),
+            )
+            .text_size(12);
+            let mut delivery_row = row![delivery_label, delivery_picker]
+                .spacing(10)
+                .align_y(Center);
+            if command.delivery == DeliveryMode::Announcement {
+                let color_picker = pick_list(
+                    &AnnouncementColor::ALL[..],
+                    Some(command.announcement_color),
+                    move |color| Message::CommandAnnouncementColorChanged(index, color),
+                )
+                .text_size(12);
+                delivery_row = delivery_row.push(color_picker);
+            }
+            if let Some(info) = &self.token_info
+                && let Some(scope) = delivery_scope(command.delivery)
+                && !info.has_scope(scope)
+            {
+                delivery_row = delivery_row.push(
+                    text(format!(
+                        "Needs {} - log in again, replies until then",
+                        scope
+                    ))
+                    .size(11)
+                    .color(p.status_error),
+                );
+            }
+
+            settings_content = settings_content
+                .push(container(text("")).height(5))
+                .push(trigger_row)
+                .push(aliases_row)
+                .push(template_row)
+                .push(delivery_row)
+                .push(self.build_template_preview(&command.template, p));
+        }
+
+        let add_btn = button(text("Add command").size(12))
+            .style(primary_button)
+            .on_press(Message::AddCommand);
+        let reset_btn = button(text("Reset to defaults").size(12))
+            .style(primary_button)
+            .on_press(Message::ResetCommands);
+
+        settings_content = settings_content
+            .push(container(text("")).height(5))
+            .push(row![add_btn, reset_btn].spacing(10));
+
+        let responses_header = text("Bot Responses").size(14);
+        let language_row = row![
+            text("Language:").size(12),
+            pick_list(
+                &Language::ALL[..],
+                self.responses.language(),
+                Message::ResponseLanguageSelected,
+            )
+            .placeholder("Custom")
+            .text_size(12)
+        ]
+        .spacing(10)
+        .align_y(Center);
+        let responses_help = text(
+            "Messages the bot sends besides the command formats. Picking a language replaces all of them.",
+        )
+        .size(11)
+        .color(p.text_secondary);
+
+        settings_content = settings_content
+            .push(container(text("")).height(15))
+            .push(responses_header)
+            .push(language_row)
+            .push(responses_help);
+
+        for field in ResponseField::ALL {
+            let input = text_input("", self.responses.get(field))
+                .size(12)
+                .width(Fill)
+                .style(primary_text_input)
+                .on_input(move |value| Message::ResponseChanged(field, value));
+            settings_content = settings_content.push(
+                row![text(field.label()).size(12).width(180), input]
+                    .spacing(10)
+                    .align_y(Center),
+            );
+        }
+        for status in BeatmapStatus::ALL {
+            let input = text_input("", self.responses.statuses.get(status))
+                .size(12)
+                .width(Fill)
+                .style(primary_text_input)
+                .on_input(move |value| Message::StatusNameChanged(status, value));
+            settings_content = settings_content.push(
+                row![
+                    text(format!("Status {}", status)).size(12).width(180),
+                    input
+                ]
+                .spacing(10)
+                .align_y(Center),
+            );
+        }
+
         scrollable(container(settings_content).width(Fill))
             .height(Fill)
             .into()
     }
 
+    fn view_requests_tab(&self, p: &ColorPalette) -> Element<'_, Message> {
+        let accept_checkbox = checkbox(self.accept_requests)
+            .label("Accept beatmap requests from links in chat")
+            .on_toggle(Message::AcceptRequestsToggled)
+            .size(14)
+            .text_size(12);
+        let role_picker = row![
+            text("Allowed:").size(12),
+            pick_list(
+                &ChatRole::ALL[..],
+                Some(self.request_min_role),
+                Message::RequestRoleSelected
+            )
+            .text_size(12),
+        ]
+        .spacing(10)
+        .align_y(Center);
+
+        let list: Element<'_, Message> = if self.requests.is_empty() {
+            let no_requests = text("No requests in the queue")
+                .size(12)
+                .color(p.text_secondary);
+            let hint = text("Viewers can request a beatmap by posting its osu.ppy.sh link in chat")
+                .size(11)
+                .color(p.text_muted);
+            column![no_requests, hint].spacing(5).into()
+        } else {
+            column(self.requests.iter().enumerate().map(|(index, request)| {
+                let link = request.web_link();
+                row![
+                    text(format!("#{}", index + 1))
+                        .size(11)
+                        .color(p.text_secondary)
+                        .width(30),
+                    rich_text![
+                        span::<String, Font>(link.clone())
+                            .color(p.accent_alt)
+                            .underline(true)
+                            .link(link)
+                    ]
+                    .size(11)
+                    .on_link_click(Message::LinkClicked),
+                    text(format!("by {}", request.requester))
+                        .size(11)
+                        .color(p.text_secondary),
+                    container(text("")).width(Fill),
+                    button(text("Open").size(12))
+                        .style(primary_button)
+                        .on_press(Message::LinkClicked(request.osu_link())),
+                    button(text("Approve").size(12))
+                        .style(primary_button)
+                        .on_press(Message::ApproveRequest(request.id)),
+                    button(text("Skip").size(12))
+                        .style(primary_button)
+                        .on_press(Message::SkipRequest(request.id)),
+                ]
+                .spacing(10)
+                .align_y(Center)
+                .into()
+            }))
+            .spacing(6)
+            .into()
+        };
+
+        scrollable(
+            column![accept_checkbox, role_picker, list]
+                .spacing(15)
+                .padding(10),
+        )
+        .height(Fill)
+        .width(Fill)
+        .into()
+    }
+
+    fn view_test_chat_tab(&self, p: &ColorPalette) -> Element<'_, Message> {
+        let login_input = text_input(DEFAULT_TEST_LOGIN, &self.test_chat_login)
+            .size(12)
+            .width(100)
+            .style(primary_text_input)
+            .on_input(Message::TestChatLoginChanged);
+        let mut user_row = row![text("User:").size(12), login_input]
+            .spacing(10)
+            .align_y(Center);
+        for role in TEST_CHAT_ROLES {
+            let label = match role {
+                ChatRole::Broadcaster => "Broadcaster",
+                ChatRole::Moderator => "Mod",
+                ChatRole::Vip => "VIP",
+                _ => "Sub",
+            };
+            user_row = user_row.push(
+                checkbox(self.test_chat_roles.contains(&role))
+                    .label(label)
+                    .on_toggle(move |value| Message::TestChatRoleToggled(role, value))
+                    .size(14)
+                    .text_size(12),
+            );
+        }
+
+        let transcript: Element<'_, Message> = if self.test_chat_lines.is_empty() {
+            let placeholder = text(
+                "Messages run through the bot like real chat, using the current osu! data, but nothing is sent to Twitch. Moderator commands and beatmap links still take effect.",
+            )
+            .size(11)
+            .color(p.text_muted);
+            center_y(center_x(placeholder)).height(Fill).into()
+        } else {
+            let lines = column(self.test_chat_lines.iter().map(|line| {
+                let (sender, color) = match &line.sender {
+                    Some(sender) => (sender.as_str(), p.text_secondary),
+                    None => ("bot", p.accent_alt),
+                };
+                rich_text![
+                    span::<String, Font>(format!("{}: ", sender)).color(color),
+                    span::<String, Font>(&line.text).color(p.text_primary),
+                ]
+                .size(11)
+                .into()
+            }))
+            .spacing(2)
+            .padding(10);
+            scrollable(lines).height(Fill).width(Fill).into()
+        };
+        let transcript = container(transcript)
+            .height(Fill)
+            .width(Fill)
+            .style(code_block_container);
+
+        let message_input = text_input("!np", &self.test_chat_input)
+            .size(12)
+            .width(Fill)
+            .style(primary_text_input)
+            .on_input(Message::TestChatInputChanged)
+            .on_submit(Message::SendTestChat);
+        let send_btn = button(text("Send").size(12))
+            .style(primary_button)
+            .on_press(Message::SendTestChat);
+        let clear_btn = button(text("Clear").size(12))
+            .style(primary_button)
+            .on_press(Message::ClearTestChat);
+        let input_row = row![message_input, send_btn, clear_btn]
+            .spacing(10)
+            .align_y(Center);
+
+        column![user_row, transcript, input_row]
+            .spacing(10)
+            .padding(10)
+            .into()
+    }
+
     fn view_data_tab(&self, p: &ColorPalette) -> Element<'_, Message> {
         let content = match &self.current_beatmap {
             Some(beatmap) => {
@@ -538,14 +1186,19 @@ impl State {
             .into()
     }
 
-    fn build_np_format_preview(&self, p: &ColorPalette) -> Element<'_, Message> {
-        let placeholders = self
-            .current_beatmap
-            .as_ref()
-            .map(Placeholders::from_beatmap)
-            .unwrap_or_else(Placeholders::sample);
+    fn build_template_preview(&self, template: &str, p: &ColorPalette) -> Element<'_, Message> {
+        let placeholders = match &self.current_beatmap {
+            Some(beatmap) => {
+                let placeholders = Placeholders::from_beatmap(beatmap, &self.responses.statuses);
+                match &self.cached_pp {
+                    Some(pp) => placeholders.with_pp(pp),
+                    None => placeholders,
+                }
+            }
+            None => Placeholders::sample(),
+        };
 
-        let preview_text = placeholders.apply_np(&self.np_format);
+        let preview_text = placeholders.apply(template);
 
         let preview_label = span::<String, Font>("Preview: ").color(p.text_secondary);
         let preview_content = span::<String, Font>(preview_text).color(p.text_primary);
@@ -559,24 +1212,144 @@ impl State {
             .into()
     }
 
-    fn build_pp_format_preview(&self, p: &ColorPalette) -> Element<'_, Message> {
-        let placeholders = match (&self.current_beatmap, &self.cached_pp) {
-            (Some(beatmap), Some(pp)) => Placeholders::from_beatmap(beatmap).with_pp(pp),
-            _ => Placeholders::sample_pp(),
+    fn save_commands(&mut self) {
+        if let Err(e) = PreferencesStore::set_commands(self.commands.clone()) {
+            log_warn!("gui", "Failed to save commands: {}", e);
+        }
+        let _ = self
+            .twitch_cmd_tx
+            .try_send(TwitchCommand::UpdatePreferences(PreferencesUpdate {
+                commands: Some(self.commands.clone()),
+                ..Default::default()
+            }));
+    }
+
+    fn save_request_reward(&mut self) {
+        if let Err(e) = PreferencesStore::set_request_reward_id(self.request_reward_id.clone()) {
+            log_warn!("gui", "Failed to save request reward: {}", e);
+        }
+        let _ = self
+            .twitch_cmd_tx
+            .try_send(TwitchCommand::UpdatePreferences(PreferencesUpdate {
+                request_reward_id: Some(self.request_reward_id.clone()),
+                ..Default::default()
+            }));
+    }
+
+    fn save_request_role(&mut self) {
+        if let Err(e) = PreferencesStore::set_request_min_role(self.request_min_role) {
+            log_warn!("gui", "Failed to save request role: {}", e);
+        }
+        let _ = self
+            .twitch_cmd_tx
+            .try_send(TwitchCommand::UpdatePreferences(PreferencesUpdate {
+                request_min_role: Some(self.request_min_role),
+                ..Default::default()
+            }));
+    }
+
+    fn resolve_request(&mut self, request: &BeatmapRequest, approved: bool) {
+        let command = match &request.source {
+            RequestSource::Redemption {
+                reward_id,
+                redemption_id,
+            } => TwitchCommand::ResolveRedemption {
+                reward_id: reward_id.clone(),
+                redemption_id: redemption_id.clone(),
+                fulfilled: approved,
+            },
+            RequestSource::Chat { message_id } if approved => TwitchCommand::ConfirmRequest {
+                message_id: message_id.clone(),
+            },
+            RequestSource::Chat { .. } => return,
         };
+        if let Err(e) = self.twitch_cmd_tx.try_send(command) {
+            log_warn!("gui", "Failed to send request update: {}", e);
+        }
+    }
 
-        let preview_text = placeholders.apply_pp(&self.pp_format);
+    fn save_cooldowns(&mut self) {
+        if let Err(e) = PreferencesStore::set_cooldowns(self.cooldowns.clone()) {
+            log_warn!("gui", "Failed to save cooldowns: {}", e);
+        }
+        let _ = self
+            .twitch_cmd_tx
+            .try_send(TwitchCommand::UpdatePreferences(PreferencesUpdate {
+                cooldowns: Some(self.cooldowns.clone()),
+                ..Default::default()
+            }));
+    }
 
-        let preview_label = span::<String, Font>("Preview: ").color(p.text_secondary);
-        let preview_content = span::<String, Font>(preview_text).color(p.text_primary);
+    fn save_announcements(&mut self) {
+        if let Err(e) = PreferencesStore::set_announcements(self.announcements.clone()) {
+            log_warn!("gui", "Failed to save announcements: {}", e);
+        }
+        let _ = self
+            .twitch_cmd_tx
+            .try_send(TwitchCommand::UpdatePreferences(PreferencesUpdate {
+                announcements: Some(self.announcements.clone()),
+                ..Default::default()
+            }));
+    }
 
-        let preview_rich_text = rich_text![preview_label, preview_content].size(11);
+    fn save_bot_enabled(&mut self) {
+        if let Err(e) = PreferencesStore::set_bot_enabled(self.bot_enabled) {
+            log_warn!("gui", "Failed to save bot enabled preference: {}", e);
+        }
+        let _ = self
+            .twitch_cmd_tx
+            .try_send(TwitchCommand::UpdatePreferences(PreferencesUpdate {
+                enabled: Some(self.bot_enabled),
+                ..Default::default()
+            }));
+    }
 
-        container(preview_rich_text)
-            .padding(8)
-            .width(Fill)
-            .style(code_block_container)
-            .into()
+    fn save_ignored_users(&mut self) {
+        if let Err(e) = PreferencesStore::set_ignored_users(self.ignored_users.clone()) {
+            log_warn!("gui", "Failed to save ignored users: {}", e);
+        }
+        let _ = self
+            .twitch_cmd_tx
+            .try_send(TwitchCommand::UpdatePreferences(PreferencesUpdate {
+                ignored_users: Some(self.ignored_users.clone()),
+                ..Default::default()
+            }));
+    }
+
+    fn save_shared_chat(&mut self) {
+        if let Err(e) = PreferencesStore::set_shared_chat(self.shared_chat.clone()) {
+            log_warn!("gui", "Failed to save shared chat settings: {}", e);
+        }
+        let _ = self
+            .twitch_cmd_tx
+            .try_send(TwitchCommand::UpdatePreferences(PreferencesUpdate {
+                shared_chat: Some(self.shared_chat.clone()),
+                ..Default::default()
+            }));
+    }
+
+    fn save_responses(&mut self) {
+        if let Err(e) = PreferencesStore::set_responses(self.responses.clone()) {
+            log_warn!("gui", "Failed to save bot responses: {}", e);
+        }
+        let _ = self
+            .twitch_cmd_tx
+            .try_send(TwitchCommand::UpdatePreferences(PreferencesUpdate {
+                responses: Some(self.responses.clone()),
+                ..Default::default()
+            }));
+    }
+
+    fn save_long_messages(&mut self) {
+        if let Err(e) = PreferencesStore::set_long_messages(self.long_messages) {
+            log_warn!("gui", "Failed to save long message handling: {}", e);
+        }
+        let _ = self
+            .twitch_cmd_tx
+            .try_send(TwitchCommand::UpdatePreferences(PreferencesUpdate {
+                long_messages: Some(self.long_messages),
+                ..Default::default()
+            }));
     }
 
     fn view_footer(&self, p: &ColorPalette) -> Element<'_, Message> {
@@ -621,6 +1394,16 @@ impl State {
                     log_warn!("gui", "Failed to save auto-connect preference: {}", e);
                 }
             }
+            Message::BotEnabledToggled(value) => {
+                self.bot_enabled = value;
+                self.save_bot_enabled();
+            }
+            Message::ChannelLoginChanged(value) => {
+                self.channel_login = value;
+                if let Err(e) = PreferencesStore::set_channel_login(self.channel_login.clone()) {
+                    log_warn!("gui", "Failed to save channel preference: {}", e);
+                }
+            }
             Message::TokenHelpClicked => {
                 let _ = open::that("https://osu-twitchbot.dyl.blue/");
             }
@@ -649,19 +1432,24 @@ impl State {
 
                 self.twitch_status = TwitchStatus::Connecting;
 
-                if let Err(e) = CredentialStore::save_token(&token) {
-                    log_warn!("gui", "Failed to save token to credential store: {}", e);
-                } else {
-                    log_debug!("gui", "Token saved to credential store");
-                    self.token_saved = true;
+                if !self.token_input_value.is_empty() {
+                    // a pasted token comes without a refresh token, so any stored one
+                    // belongs to a previous login
+                    if let Err(e) = CredentialStore::save_token(&token)
+                        .and_then(|()| CredentialStore::delete_refresh_token())
+                    {
+                        log_warn!("gui", "Failed to save token to credential store: {}", e);
+                    } else {
+                        log_debug!("gui", "Token saved to credential store");
+                        self.token_saved = true;
+                        self.token_input_value.clear();
+                    }
                 }
 
                 if let Err(e) = self.twitch_cmd_tx.try_send(TwitchCommand::Connect {
                     token,
-                    np_command: self.np_command.clone(),
-                    np_format: self.np_format.clone(),
-                    pp_command: self.pp_command.clone(),
-                    pp_format: self.pp_format.clone(),
+                    settings: self.chatbot_settings(),
+                    channel_login: self.channel_login.clone(),
                 }) {
                     log_error!("gui", "Failed to send connect command: {}", e);
                     self.twitch_status =
@@ -677,133 +1465,233 @@ impl State {
             }
             Message::ClearTokenClicked => {
                 log_debug!("gui", "Clear token clicked");
-                if let Err(e) = CredentialStore::delete_token() {
+                if let Err(e) = CredentialStore::delete_token()
+                    .and_then(|()| CredentialStore::delete_refresh_token())
+                {
                     log_warn!("gui", "Failed to delete token from credential store: {}", e);
                 } else {
                     log_debug!("gui", "Token deleted from credential store");
                 }
                 self.token_input_value.clear();
                 self.token_saved = false;
+                self.token_info = None;
             }
-            Message::NpCommandChanged(value) => {
-                log_debug!("gui", "Changed np_command to {}", value);
-                self.np_command = value;
-                if let Err(e) = PreferencesStore::set_np_command(self.np_command.clone()) {
-                    log_warn!("gui", "Failed to save np_command: {}", e);
+            Message::LoginClicked => {
+                log_debug!("gui", "Login clicked");
+                self.login_state = LoginState::Requesting;
+                if let Err(e) = self.twitch_cmd_tx.try_send(TwitchCommand::Login) {
+                    log_error!("gui", "Failed to send login command: {}", e);
+                    self.login_state = LoginState::Idle;
                 }
-                let _ = self
-                    .twitch_cmd_tx
-                    .try_send(TwitchCommand::UpdatePreferences {
-                        np_command: Some(self.np_command.clone()),
-                        np_format: None,
-                        pp_command: None,
-                        pp_format: None,
-                    });
             }
-            Message::NpFormatChanged(value) => {
-                log_debug!("gui", "Changed np_format to {}", value);
-                self.np_format = value;
-                if let Err(e) = PreferencesStore::set_np_format(self.np_format.clone()) {
-                    log_warn!("gui", "Failed to save np_format: {}", e);
+            Message::CancelLoginClicked => {
+                log_debug!("gui", "Cancel login clicked");
+                self.login_state = LoginState::Idle;
+                let _ = self.twitch_cmd_tx.try_send(TwitchCommand::CancelLogin);
+            }
+            Message::CommandTriggerChanged(index, value) => {
+                if let Some(command) = self.commands.get_mut(index) {
+                    log_debug!("gui", "Changed command {} trigger to {}", index, value);
+                    command.trigger = value;
+                    self.save_commands();
                 }
-                let _ = self
-                    .twitch_cmd_tx
-                    .try_send(TwitchCommand::UpdatePreferences {
-                        np_command: None,
-                        np_format: Some(self.np_format.clone()),
-                        pp_command: None,
-                        pp_format: None,
-                    });
             }
-            Message::ResetNpCommand => {
-                log_debug!("gui", "Reset np_command to default");
-                self.np_command = DEFAULT_NP_COMMAND.to_string();
-                if let Err(e) = PreferencesStore::set_np_command(self.np_command.clone()) {
-                    log_warn!("gui", "Failed to save np_command: {}", e);
+            Message::CommandAliasesChanged(index, value) => {
+                if let Some(command) = self.commands.get_mut(index) {
+                    log_debug!("gui", "Changed command {} aliases to {}", index, value);
+                    command.aliases = if value.is_empty() {
+                        Vec::new()
+                    } else {
+                        value.split(',').map(str::to_string).collect()
+                    };
+                    self.save_commands();
                 }
-                let _ = self
-                    .twitch_cmd_tx
-                    .try_send(TwitchCommand::UpdatePreferences {
-                        np_command: Some(self.np_command.clone()),
-                        np_format: None,
-                        pp_command: None,
-                        pp_format: None,
-                    });
             }
-            Message::ResetNpFormat => {
-                log_debug!("gui", "Reset np_format to default");
-                self.np_format = DEFAULT_NP_FORMAT.to_string();
-                if let Err(e) = PreferencesStore::set_np_format(self.np_format.clone()) {
-                    log_warn!("gui", "Failed to save np_format: {}", e);
+            Message::CommandTemplateChanged(index, value) => {
+                if let Some(command) = self.commands.get_mut(index) {
+                    log_debug!("gui", "Changed command {} template to {}", index, value);
+                    command.template = value;
+                    self.save_commands();
                 }
-                let _ = self
-                    .twitch_cmd_tx
-                    .try_send(TwitchCommand::UpdatePreferences {
-                        np_command: None,
-                        np_format: Some(self.np_format.clone()),
-                        pp_command: None,
-                        pp_format: None,
-                    });
             }
-            Message::PpCommandChanged(value) => {
-                log_debug!("gui", "Changed pp_command to {}", value);
-                self.pp_command = value;
-                if let Err(e) = PreferencesStore::set_pp_command(self.pp_command.clone()) {
-                    log_warn!("gui", "Failed to save pp_command: {}", e);
+            Message::CommandToggled(index, value) => {
+                if let Some(command) = self.commands.get_mut(index) {
+                    log_debug!("gui", "Set command {} enabled to {}", index, value);
+                    command.enabled = value;
+                    self.save_commands();
                 }
-                let _ = self
-                    .twitch_cmd_tx
-                    .try_send(TwitchCommand::UpdatePreferences {
-                        np_command: None,
-                        np_format: None,
-                        pp_command: Some(self.pp_command.clone()),
-                        pp_format: None,
-                    });
             }
-            Message::PpFormatChanged(value) => {
-                log_debug!("gui", "Changed pp_format to {}", value);
-                self.pp_format = value;
-                if let Err(e) = PreferencesStore::set_pp_format(self.pp_format.clone()) {
-                    log_warn!("gui", "Failed to save pp_format: {}", e);
+            Message::CommandRoleChanged(index, role) => {
+                if let Some(command) = self.commands.get_mut(index) {
+                    log_debug!("gui", "Set command {} minimum role to {}", index, role);
+                    command.min_role = role;
+                    self.save_commands();
                 }
-                let _ = self
-                    .twitch_cmd_tx
-                    .try_send(TwitchCommand::UpdatePreferences {
-                        np_command: None,
-                        np_format: None,
-                        pp_command: None,
-                        pp_format: Some(self.pp_format.clone()),
-                    });
             }
-            Message::ResetPpCommand => {
-                log_debug!("gui", "Reset pp_command to default");
-                self.pp_command = DEFAULT_PP_COMMAND.to_string();
-                if let Err(e) = PreferencesStore::set_pp_command(self.pp_command.clone()) {
-                    log_warn!("gui", "Failed to save pp_command: {}", e);
+            Message::CommandDeliveryChanged(index, mode) => {
+                if let Some(command) = self.commands.get_mut(index) {
+                    log_debug!("gui", "Set command {} delivery to {}", index, mode);
+                    command.delivery = mode;
+                    self.save_commands();
                 }
-                let _ = self
-                    .twitch_cmd_tx
-                    .try_send(TwitchCommand::UpdatePreferences {
-                        np_command: None,
-                        np_format: None,
-                        pp_command: Some(self.pp_command.clone()),
-                        pp_format: None,
-                    });
             }
-            Message::ResetPpFormat => {
-                log_debug!("gui", "Reset pp_format to default");
-                self.pp_format = DEFAULT_PP_FORMAT.to_string();
-                if let Err(e) = PreferencesStore::set_pp_format(self.pp_format.clone()) {
-                    log_warn!("gui", "Failed to save pp_format: {}", e);
+            Message::CommandAnnouncementColorChanged(index, color) => {
+                if let Some(command) = self.commands.get_mut(index) {
+                    log_debug!(
+                        "gui",
+                        "Set command {} announcement color to {}",
+                        index,
+                        color
+                    );
+                    command.announcement_color = color;
+                    self.save_commands();
+                }
+            }
+            Message::AddCommand => {
+                log_debug!("gui", "Added command");
+                self.commands.push(CustomCommand::new("", ""));
+                self.save_commands();
+            }
+            Message::RemoveCommand(index) => {
+                if index < self.commands.len() {
+                    let removed = self.commands.remove(index);
+                    log_debug!("gui", "Removed command {}", removed.trigger);
+                    self.save_commands();
+                }
+            }
+            Message::ResetCommands => {
+                log_debug!("gui", "Reset commands to defaults");
+                self.commands = default_commands();
+                self.save_commands();
+            }
+            Message::GlobalCooldownChanged(value) => {
+                if let Some(seconds) = parse_seconds(&value) {
+                    log_debug!("gui", "Changed global cooldown to {}s", seconds);
+                    self.cooldowns.global_seconds = seconds;
+                    self.save_cooldowns();
+                }
+            }
+            Message::UserCooldownChanged(value) => {
+                if let Some(seconds) = parse_seconds(&value) {
+                    log_debug!("gui", "Changed per-user cooldown to {}s", seconds);
+                    self.cooldowns.user_seconds = seconds;
+                    self.save_cooldowns();
+                }
+            }
+            Message::CooldownBypassToggled(value) => {
+                self.cooldowns.privileged_bypass = value;
+                self.save_cooldowns();
+            }
+            Message::CooldownReplyToggled(value) => {
+                self.cooldowns.reply_on_cooldown = value;
+                self.save_cooldowns();
+            }
+            Message::AnnounceToggled(value) => {
+                self.announcements.enabled = value;
+                self.save_announcements();
+            }
+            Message::AnnounceDelayChanged(value) => {
+                if let Some(seconds) = parse_seconds(&value) {
+                    self.announcements.delay_seconds = seconds;
+                    self.save_announcements();
+                }
+            }
+            Message::AnnounceIntervalChanged(value) => {
+                if let Some(seconds) = parse_seconds(&value) {
+                    self.announcements.min_interval_seconds = seconds;
+                    self.save_announcements();
+                }
+            }
+            Message::AnnounceTemplateChanged(value) => {
+                self.announcements.template = value;
+                self.save_announcements();
+            }
+            Message::IgnoredUsersChanged(value) => {
+                self.ignored_users = if value.is_empty() {
+                    Vec::new()
+                } else {
+                    value.split(',').map(str::to_string).collect()
+                };
+                self.save_ignored_users();
+            }
+            Message::SharedChatModeSelected(mode) => {
+                self.shared_chat.mode = mode;
+                self.save_shared_chat();
+            }
+            Message::SharedChatChannelsChanged(value) => {
+                self.shared_chat.channels = if value.is_empty() {
+                    Vec::new()
+                } else {
+                    value.split(',').map(str::to_string).collect()
+                };
+                self.save_shared_chat();
+            }
+            Message::ResponseLanguageSelected(language) => {
+                log_debug!("gui", "Switched bot responses to {}", language);
+                self.responses = language.responses();
+                self.save_responses();
+            }
+            Message::ResponseChanged(field, value) => {
+                self.responses.set(field, value);
+                self.save_responses();
+            }
+            Message::StatusNameChanged(status, value) => {
+                self.responses.statuses.set(status, value);
+                self.save_responses();
+            }
+            Message::LongMessagesSelected(mode) => {
+                self.long_messages = mode;
+                self.save_long_messages();
+            }
+            Message::AcceptRequestsToggled(value) => {
+                self.accept_requests = value;
+                requests::set_accepting(value);
+                if let Err(e) = PreferencesStore::set_accept_requests(value) {
+                    log_warn!("gui", "Failed to save request preference: {}", e);
                 }
+            }
+            Message::RequestRoleSelected(role) => {
+                self.request_min_role = role;
+                self.save_request_role();
+            }
+            Message::ApproveRequest(id) => {
+                if let Some(request) = requests::remove(id) {
+                    log_info!(
+                        "requests",
+                        "Approved beatmap {} requested by {}",
+                        request.beatmap_id,
+                        request.requester
+                    );
+                    self.resolve_request(&request, true);
+                }
+                self.requests = requests::snapshot();
+            }
+            Message::SkipRequest(id) => {
+                if let Some(request) = requests::remove(id) {
+                    log_info!(
+                        "requests",
+                        "Skipped beatmap {} requested by {}",
+                        request.beatmap_id,
+                        request.requester
+                    );
+                    self.resolve_request(&request, false);
+                }
+                self.requests = requests::snapshot();
+            }
+            Message::RequestRewardSelected(reward) => {
+                log_debug!("gui", "Selected request reward {}", reward.title);
+                self.request_reward_id = reward.id;
+                self.save_request_reward();
+            }
+            Message::CreateRequestReward => {
                 let _ = self
                     .twitch_cmd_tx
-                    .try_send(TwitchCommand::UpdatePreferences {
-                        np_command: None,
-                        np_format: None,
-                        pp_command: None,
-                        pp_format: Some(self.pp_format.clone()),
-                    });
+                    .try_send(TwitchCommand::CreateRequestReward);
+            }
+            Message::ClearRequestReward => {
+                log_debug!("gui", "Cleared request reward");
+                self.request_reward_id.clear();
+                self.save_request_reward();
             }
             Message::OsuEvent(event) => match event {
                 MemoryEvent::StatusChanged(ref status) => {
@@ -831,13 +1719,34 @@ impl State {
                     });
                     self.current_beatmap = beatmap;
                 }
-                MemoryEvent::BeatmapDataResponse(_) => {}
+                MemoryEvent::BeatmapDataResponse(..) => {}
             },
             Message::TwitchEvent(event) => match event {
                 TwitchEvent::Connected(ref username) => {
                     log_info!("twitch", "Connected to Twitch as {}", username);
                     self.twitch_status = TwitchStatus::Connected(username.clone());
                 }
+                TwitchEvent::TokenValidated(info) => {
+                    let missing_scopes = info.missing_scopes();
+                    if !missing_scopes.is_empty() {
+                        log_warn!(
+                            "twitch",
+                            "Token is missing required scopes: {}",
+                            missing_scopes.join(", ")
+                        );
+                    }
+                    if info.expires_soon() {
+                        log_warn!(
+                            "twitch",
+                            "Token expires in {}",
+                            format_expiry(info.expires_in)
+                        );
+                    }
+                    self.token_info = Some(info);
+                }
+                TwitchEvent::Reconnecting(attempt) => {
+                    self.twitch_status = TwitchStatus::Reconnecting(attempt);
+                }
                 TwitchEvent::Disconnected => {
                     log_info!("twitch", "Disconnected from Twitch");
                     self.twitch_status = TwitchStatus::Disconnected;
@@ -846,6 +1755,59 @@ impl State {
                     log_error!("twitch", "Connection error: {}", e);
                     self.twitch_status = TwitchStatus::Error(e.clone());
                 }
+                TwitchEvent::LoginPending {
+                    user_code,
+                    verification_uri,
+                } => {
+                    let _ = open::that(&verification_uri);
+                    self.login_state = LoginState::Pending {
+                        user_code,
+                        verification_uri,
+                    };
+                }
+                TwitchEvent::LoggedIn => {
+                    self.login_state = LoginState::Idle;
+                    self.token_input_value.clear();
+                    self.token_saved = true;
+                    self.update(Message::ConnectClicked);
+                }
+                TwitchEvent::LoginFailed(e) => {
+                    self.login_state = LoginState::Failed(e);
+                }
+                TwitchEvent::RequestsChanged => {
+                    self.requests = requests::snapshot();
+                }
+                TwitchEvent::RewardsLoaded(rewards) => {
+                    self.rewards = rewards;
+                }
+                TwitchEvent::RewardCreated(reward) => {
+                    self.request_reward_id = reward.id.clone();
+                    self.rewards.push(reward);
+                    self.save_request_reward();
+                }
+                TwitchEvent::PreferencesChanged(update) => {
+                    if let Some(enabled) = update.enabled {
+                        self.bot_enabled = enabled;
+                        self.save_bot_enabled();
+                    }
+                    if let Some(commands) = update.commands {
+                        self.commands = commands;
+                        self.save_commands();
+                    }
+                    if let Some(cooldowns) = update.cooldowns {
+                        self.cooldowns = cooldowns;
+                        self.save_cooldowns();
+                    }
+                }
+                TwitchEvent::TestChatAnswered(reply) => {
+                    let text = match reply {
+                        Some(reply) if reply.delivery == DeliveryMode::Reply => reply.text,
+                        Some(reply) => format!("({}) {}", reply.delivery, reply.text),
+                        None => "(no response)".to_string(),
+                    };
+                    self.test_chat_lines
+                        .push(TestChatLine { sender: None, text });
+                }
             },
             Message::LogEvent(entry) => {
                 self.log_entries.push(entry);
@@ -854,9 +1816,55 @@ impl State {
                     self.log_entries.remove(0);
                 }
             }
+            Message::TestChatLoginChanged(value) => {
+                self.test_chat_login = value;
+            }
+            Message::TestChatRoleToggled(role, value) => {
+                self.test_chat_roles.retain(|r| *r != role);
+                if value {
+                    self.test_chat_roles.push(role);
+                }
+            }
+            Message::TestChatInputChanged(value) => {
+                self.test_chat_input = value;
+            }
+            Message::SendTestChat => {
+                let text = std::mem::take(&mut self.test_chat_input);
+                if text.trim().is_empty() {
+                    return;
+                }
+                let login = match self.test_chat_login.trim() {
+                    "" => DEFAULT_TEST_LOGIN.to_string(),
+                    login => login.to_string(),
+                };
+                self.test_chat_lines.push(TestChatLine {
+                    sender: Some(login.clone()),
+                    text: text.clone(),
+                });
+                let _ = self.twitch_cmd_tx.try_send(TwitchCommand::TestChat {
+                    message: TestMessage {
+                        login,
+                        roles: self.test_chat_roles.clone(),
+                        text,
+                    },
+                    settings: self.chatbot_settings(),
+                    beatmap: self.current_beatmap.clone().map(Box::new),
+                });
+            }
+            Message::ClearTestChat => {
+                self.test_chat_lines.clear();
+            }
             Message::LinkClicked(url) => {
                 let _ = open::that(url);
             }
         }
     }
 }
+
+fn parse_seconds(value: &str) -> Option<u64> {
+    if value.trim().is_empty() {
+        Some(0)
+    } else {
+        value.trim().parse().ok()
+    }
+}
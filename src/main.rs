@@ -11,12 +11,16 @@ use iced::window;
 use iced::{Subscription, stream};
 use tokio::time::{self, Duration};
 
+mod commands;
 mod credentials;
 mod gui;
 mod logging;
 mod osu;
 mod placeholders;
 mod preferences;
+mod requests;
+mod responses;
+mod text;
 mod twitch;
 mod updater;
 
@@ -29,7 +33,9 @@ use osu::core::{
 };
 use osu::lazer::run_lazer_reader;
 use osu::stable::run_stable_reader;
-use twitch::{TwitchClient, TwitchCommand, TwitchEvent};
+use twitch::auth::run_device_login;
+use twitch::core::{ChatbotPreferences, ChatbotSettings, TwitchClient, TwitchCommand, TwitchEvent};
+use twitch::dispatch::{ChatDispatcher, Dispatched};
 #[cfg(not(debug_assertions))]
 use updater::core::is_auto_update_enabled;
 use updater::core::set_auto_update_enabled;
@@ -50,6 +56,12 @@ fn main() -> iced::Result {
 
     log_info!("main", "Starting osu-twitchbot");
 
+    if args_mock_twitch()
+        && let Err(e) = twitch::mock::spawn()
+    {
+        log_error!("main", "Failed to start mock Twitch server: {}", e);
+    }
+
     let icon = window::icon::from_file_data(
         include_bytes!("../assets/icon.png"),
         Some(image::ImageFormat::Png),
@@ -155,8 +167,11 @@ fn osu_worker() -> impl iced::futures::Stream<Item = MemoryEvent> {
             let process: DetectedProcess = loop {
                 if let Ok(Some(cmd)) = cmd_rx.try_next() {
                     match cmd {
-                        OsuCommand::RequestBeatmapData => {
-                            let event = MemoryEvent::BeatmapDataResponse(current_beatmap.clone());
+                        OsuCommand::RequestBeatmapData(request_id) => {
+                            let event = MemoryEvent::BeatmapDataResponse(
+                                request_id,
+                                current_beatmap.clone(),
+                            );
                             let _ = tx.send(event.clone()).await;
                             let _ = forward_tx.send(event).await;
                         }
@@ -229,16 +244,18 @@ fn twitch_worker() -> impl iced::futures::Stream<Item = TwitchEvent> {
         let (osu_tx, _) = get_osu_channel();
 
         let mut websocket_handle: Option<tokio::task::JoinHandle<()>> = None;
+        let mut login_handle: Option<tokio::task::JoinHandle<()>> = None;
         let mut current_client: Option<Arc<TwitchClient>> = None;
+        // kept across test messages so cooldowns can be tried out
+        let mut test_dispatcher =
+            ChatDispatcher::sandboxed(ChatbotPreferences::new(ChatbotSettings::default()));
 
         while let Some(cmd) = cmd_rx.next().await {
             match cmd {
                 TwitchCommand::Connect {
                     token,
-                    np_command,
-                    np_format,
-                    pp_command,
-                    pp_format,
+                    settings,
+                    channel_login,
                 } => {
                     // clean up any existing connections
                     if let Some(handle) = websocket_handle.take() {
@@ -246,20 +263,32 @@ fn twitch_worker() -> impl iced::futures::Stream<Item = TwitchEvent> {
                     }
                     current_client = None;
 
-                    let result =
-                        TwitchClient::new(&token, np_command, np_format, pp_command, pp_format)
-                            .await;
-                    match result {
+                    match TwitchClient::new(&token, settings, &channel_login).await {
                         Ok(client) => {
                             let client = Arc::new(client);
                             let display_name = client.user.display_name.clone();
-                            let user_id = client.user.id.clone();
+                            let token_info = client.token_info.clone();
+                            let channel_id = client.channel.id.clone();
 
                             let subscribe_result =
-                                client.subscribe_to_channel_messages(&user_id).await;
+                                client.subscribe_to_channel_messages(&channel_id).await;
 
                             match subscribe_result {
                                 Ok(()) => {
+                                    client.subscribe_to_redemptions().await;
+                                    if client.is_broadcaster() {
+                                        match client.get_custom_rewards().await {
+                                            Ok(rewards) => {
+                                                let _ = tx
+                                                    .send(TwitchEvent::RewardsLoaded(rewards))
+                                                    .await;
+                                            }
+                                            Err(e) => {
+                                                log_debug!("twitch", "No custom rewards: {}", e);
+                                            }
+                                        }
+                                    }
+
                                     // create a new channel and update the osu worker with it
                                     let (new_forward_tx, osu_event_rx) =
                                         mpsc::channel::<MemoryEvent>(10);
@@ -277,38 +306,20 @@ fn twitch_worker() -> impl iced::futures::Stream<Item = TwitchEvent> {
                                     }
 
                                     let osu_tx_clone = osu_tx.clone();
-                                    let mut tx_clone = tx.clone();
+                                    let tx_clone = tx.clone();
                                     let client_clone = Arc::clone(&client);
 
                                     let ws_handle = tokio::spawn(async move {
-                                        if let Err(e) = client_clone
-                                            .init_websocket_handler(osu_tx_clone, osu_event_rx)
-                                            .await
-                                        {
-                                            log_error!("twitch", "Websocket handler error: {}", e);
-
-                                            if e.to_string().contains("Server requested reconnect")
-                                            {
-                                                let _ = tx_clone
-                                                    .send(TwitchEvent::Error(
-                                                        "Reconnection needed - please reconnect manually"
-                                                            .to_string(),
-                                                    ))
-                                                    .await;
-                                            } else {
-                                                let _ = tx_clone
-                                                    .send(TwitchEvent::Error(e.to_string()))
-                                                    .await;
-                                            }
-                                        } else {
-                                            let _ = tx_clone.send(TwitchEvent::Disconnected).await;
-                                        }
+                                        client_clone
+                                            .run_supervised(osu_tx_clone, osu_event_rx, tx_clone)
+                                            .await;
                                     });
 
                                     websocket_handle = Some(ws_handle);
                                     current_client = Some(client);
 
                                     let _ = tx.send(TwitchEvent::Connected(display_name)).await;
+                                    let _ = tx.send(TwitchEvent::TokenValidated(token_info)).await;
                                 }
                                 Err(e) => {
                                     log_error!("twitch", "Subscription error: {:#?}", e);
@@ -332,24 +343,85 @@ fn twitch_worker() -> impl iced::futures::Stream<Item = TwitchEvent> {
 
                     let _ = tx.send(TwitchEvent::Disconnected).await;
                 }
-                TwitchCommand::UpdatePreferences {
-                    np_command,
-                    np_format,
-                    pp_command,
-                    pp_format,
-                } => {
+                TwitchCommand::Login => {
+                    if let Some(handle) = login_handle.take() {
+                        handle.abort();
+                    }
+                    login_handle = Some(tokio::spawn(run_device_login(tx.clone())));
+                }
+                TwitchCommand::CancelLogin => {
+                    if let Some(handle) = login_handle.take() {
+                        handle.abort();
+                        log_info!("twitch", "Login cancelled");
+                    }
+                }
+                TwitchCommand::UpdatePreferences(update) => {
                     if let Some(ref client) = current_client {
-                        client
-                            .update_preferences(np_command, np_format, pp_command, pp_format)
-                            .await;
+                        client.update_preferences(update).await;
                     }
                 }
+                TwitchCommand::ResolveRedemption {
+                    reward_id,
+                    redemption_id,
+                    fulfilled,
+                } => match current_client {
+                    Some(ref client) => {
+                        if let Err(e) = client
+                            .update_redemption_status(&reward_id, &redemption_id, fulfilled)
+                            .await
+                        {
+                            log_warn!("twitch", "Failed to update redemption: {}", e);
+                        }
+                    }
+                    None => {
+                        log_warn!("twitch", "Not connected, can't update redemption");
+                    }
+                },
+                TwitchCommand::CreateRequestReward => match current_client {
+                    Some(ref client) => match client.create_request_reward().await {
+                        Ok(reward) => {
+                            log_info!("twitch", "Created reward {}", reward.title);
+                            let _ = tx.send(TwitchEvent::RewardCreated(reward)).await;
+                        }
+                        Err(e) => {
+                            log_warn!("twitch", "{}", e);
+                        }
+                    },
+                    None => {
+                        log_warn!("twitch", "Not connected, can't create a reward");
+                    }
+                },
+                TwitchCommand::ConfirmRequest { message_id } => match current_client {
+                    Some(ref client) => client.confirm_request(&message_id).await,
+                    None => {
+                        log_warn!("twitch", "Not connected, can't confirm request");
+                    }
+                },
+                TwitchCommand::TestChat {
+                    message,
+                    settings,
+                    beatmap,
+                } => {
+                    test_dispatcher.preferences().update(settings.into()).await;
+                    let event = message.to_event();
+                    let reply = match test_dispatcher.handle_message(&event, tx.clone()).await {
+                        Dispatched::Ignored => None,
+                        Dispatched::Reply(reply) => Some(reply),
+                        Dispatched::Command(request) => {
+                            Some(test_dispatcher.answer(request, beatmap.map(|b| *b)).await)
+                        }
+                    };
+                    let _ = tx.send(TwitchEvent::TestChatAnswered(reply)).await;
+                }
             }
         }
 
         if let Some(handle) = websocket_handle {
             handle.abort();
         }
+        if let Some(handle) = login_handle {
+            handle.abort();
+        }
     })
 }
 
@@ -389,3 +461,7 @@ fn args_auto_update() -> bool {
 
     true
 }
+
+fn args_mock_twitch() -> bool {
+    std::env::args().any(|arg| arg == "--mock-twitch")
+}
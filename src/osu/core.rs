@@ -11,7 +11,7 @@ pub const DATA_POLLING_INTERVAL_MS: u64 = 100;
 
 #[derive(Debug)]
 pub enum OsuCommand {
-    RequestBeatmapData,
+    RequestBeatmapData(u64),
     UpdateEventForwardSender(mpsc::Sender<MemoryEvent>),
 }
 
@@ -19,7 +19,7 @@ pub enum OsuCommand {
 pub enum MemoryEvent {
     StatusChanged(OsuStatus),
     BeatmapChanged(Option<BeatmapData>),
-    BeatmapDataResponse(Option<BeatmapData>),
+    BeatmapDataResponse(u64, Option<BeatmapData>),
 }
 
 #[derive(Debug)]
@@ -80,7 +80,7 @@ impl Display for OsuStatus {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BeatmapStatus {
     Unknown,
     NotSubmitted,
@@ -94,6 +94,21 @@ pub enum BeatmapStatus {
     StablePending,
 }
 
+impl BeatmapStatus {
+    pub const ALL: [BeatmapStatus; 10] = [
+        BeatmapStatus::Unknown,
+        BeatmapStatus::NotSubmitted,
+        BeatmapStatus::Wip,
+        BeatmapStatus::Pending,
+        BeatmapStatus::Ranked,
+        BeatmapStatus::Approved,
+        BeatmapStatus::Qualified,
+        BeatmapStatus::Loved,
+        BeatmapStatus::Graveyard,
+        BeatmapStatus::StablePending,
+    ];
+}
+
 impl Display for BeatmapStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -137,6 +152,12 @@ pub struct BeatmapData {
     pub songs_folder: Option<String>,
 }
 
+impl BeatmapData {
+    pub fn is_playing(&self) -> bool {
+        self.mods.is_some()
+    }
+}
+
 #[cfg(windows)]
 mod platform {
     use super::MemoryError;
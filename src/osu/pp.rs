@@ -3,7 +3,7 @@ use std::path::Path;
 use rosu_pp::{Beatmap, Performance};
 use thiserror::Error;
 
-use super::core::GameplayMods;
+use super::core::{GameplayMods, ModInfo, order_mods};
 
 #[derive(Debug, Error)]
 pub enum PpError {
@@ -15,6 +15,99 @@ pub enum PpError {
     FileNotFound(String),
 }
 
+#[derive(Debug, Error, PartialEq)]
+pub enum PpQueryError {
+    #[error("{0} isn't a valid accuracy")]
+    Accuracy(String),
+    #[error("{0} isn't a valid miss count")]
+    Misses(String),
+    #[error("{0} contains unknown mods")]
+    Mods(String),
+    #[error("{0} isn't a valid argument")]
+    Unrecognized(String),
+}
+
+impl PpQueryError {
+    pub fn argument(&self) -> &str {
+        match self {
+            PpQueryError::Accuracy(arg)
+            | PpQueryError::Misses(arg)
+            | PpQueryError::Mods(arg)
+            | PpQueryError::Unrecognized(arg) => arg,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PpQuery {
+    pub accuracy: Option<f64>,
+    pub misses: Option<u32>,
+    pub mods: Option<GameplayMods>,
+}
+
+impl PpQuery {
+    /// Parses accuracy (`98.5`), misses (`3x`) and mods (`+HDDT`, `+NM` for none) in any order.
+    pub fn parse(args: &[&str]) -> Result<Self, PpQueryError> {
+        let mut query = Self::default();
+
+        for &arg in args {
+            if let Some(acronyms) = arg.strip_prefix('+') {
+                query.mods = Some(parse_mods(acronyms).ok_or(PpQueryError::Mods(arg.to_string()))?);
+            } else if let Some(count) = arg
+                .strip_suffix(['x', 'X'])
+                .or_else(|| arg.strip_suffix(['m', 'M']))
+            {
+                let misses = count
+                    .parse()
+                    .map_err(|_| PpQueryError::Misses(arg.to_string()))?;
+                query.misses = Some(misses);
+            } else if let Ok(accuracy) = arg.trim_end_matches('%').parse::<f64>() {
+                if !(0.0..=100.0).contains(&accuracy) {
+                    return Err(PpQueryError::Accuracy(arg.to_string()));
+                }
+                query.accuracy = Some(accuracy);
+            } else {
+                return Err(PpQueryError::Unrecognized(arg.to_string()));
+            }
+        }
+
+        Ok(query)
+    }
+
+    pub fn is_single(&self) -> bool {
+        self.accuracy.is_some() || self.misses.is_some()
+    }
+}
+
+fn parse_mods(acronyms: &str) -> Option<GameplayMods> {
+    let acronyms = acronyms.to_uppercase();
+    if acronyms == "NM" || acronyms == "NOMOD" {
+        return Some(GameplayMods {
+            mods: vec![],
+            mods_string: "NoMod".to_string(),
+        });
+    }
+    if acronyms.is_empty() || !acronyms.is_ascii() || !acronyms.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let mods = (0..acronyms.len())
+        .step_by(2)
+        .map(|i| {
+            let acronym = &acronyms[i..i + 2];
+            mod_bits(acronym).map(|_| ModInfo {
+                acronym: acronym.to_string(),
+                settings: None,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(GameplayMods {
+        mods,
+        mods_string: order_mods(&acronyms),
+    })
+}
+
 #[derive(Debug, Clone)]
 pub struct PpValues {
     pub pp_95: f64,
@@ -41,34 +134,36 @@ fn load_beatmap(local_path: Option<&str>, songs_folder: Option<&str>) -> Result<
     Ok(std::fs::read(&full_path)?)
 }
 
+fn mod_bits(acronym: &str) -> Option<u32> {
+    Some(match acronym {
+        "NF" => 1 << 0,           // NoFail
+        "EZ" => 1 << 1,           // Easy
+        "TD" => 1 << 2,           // TouchDevice
+        "HD" => 1 << 3,           // Hidden
+        "HR" => 1 << 4,           // HardRock
+        "SD" => 1 << 5,           // SuddenDeath
+        "DT" => 1 << 6,           // DoubleTime
+        "RX" => 1 << 7,           // Relax
+        "HT" => 1 << 8,           // HalfTime
+        "NC" => 1 << 6 | 1 << 9,  // Nightcore (/DT)
+        "FL" => 1 << 10,          // Flashlight
+        "SO" => 1 << 12,          // SpunOut
+        "AP" => 1 << 13,          // Autopilot
+        "PF" => 1 << 5 | 1 << 14, // Perfect (/SD)
+        _ => return None,
+    })
+}
+
 fn mods_to_bitflag(mods: &Option<GameplayMods>) -> u32 {
     let Some(gameplay_mods) = mods else {
         return 0;
     };
 
-    let mut bits = 0u32;
-
-    for mod_info in &gameplay_mods.mods {
-        bits |= match mod_info.acronym.as_str() {
-            "NF" => 1 << 0,           // NoFail
-            "EZ" => 1 << 1,           // Easy
-            "TD" => 1 << 2,           // TouchDevice
-            "HD" => 1 << 3,           // Hidden
-            "HR" => 1 << 4,           // HardRock
-            "SD" => 1 << 5,           // SuddenDeath
-            "DT" => 1 << 6,           // DoubleTime
-            "RX" => 1 << 7,           // Relax
-            "HT" => 1 << 8,           // HalfTime
-            "NC" => 1 << 6 | 1 << 9,  // Nightcore (/DT)
-            "FL" => 1 << 10,          // Flashlight
-            "SO" => 1 << 12,          // SpunOut
-            "AP" => 1 << 13,          // Autopilot
-            "PF" => 1 << 5 | 1 << 14, // Perfect (/SD)
-            _ => 0,
-        };
-    }
-
-    bits
+    gameplay_mods
+        .mods
+        .iter()
+        .filter_map(|mod_info| mod_bits(&mod_info.acronym))
+        .fold(0, |bits, mod_bits| bits | mod_bits)
 }
 
 pub fn get_pp_spread(
@@ -119,3 +214,118 @@ pub fn get_pp_spread(
         pp_100,
     })
 }
+
+pub fn get_pp_for_query(
+    query: &PpQuery,
+    mods: &Option<GameplayMods>,
+    local_path: Option<&str>,
+    songs_folder: Option<&str>,
+) -> Result<f64, PpError> {
+    let osu_file = load_beatmap(local_path, songs_folder)?;
+    let beatmap = Beatmap::from_bytes(&osu_file).map_err(|e| PpError::Parse(e.to_string()))?;
+
+    let mut performance = Performance::new(&beatmap).mods(mods_to_bitflag(mods));
+    if let Some(accuracy) = query.accuracy {
+        performance = performance.accuracy(accuracy);
+    }
+    if let Some(misses) = query.misses {
+        performance = performance.misses(misses);
+    }
+
+    Ok(performance.calculate().pp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn acronyms(query: &PpQuery) -> Vec<&str> {
+        query
+            .mods
+            .iter()
+            .flat_map(|mods| mods.mods.iter().map(|m| m.acronym.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_accuracy_with_and_without_percent() {
+        assert_eq!(PpQuery::parse(&["98.5"]).unwrap().accuracy, Some(98.5));
+        assert_eq!(PpQuery::parse(&["98.5%"]).unwrap().accuracy, Some(98.5));
+        assert_eq!(PpQuery::parse(&["100"]).unwrap().accuracy, Some(100.0));
+    }
+
+    #[test]
+    fn rejects_out_of_range_accuracy() {
+        for arg in ["100.1", "-1", "nan", "inf"] {
+            assert_eq!(
+                PpQuery::parse(&[arg]),
+                Err(PpQueryError::Accuracy(arg.to_string())),
+                "{arg}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_misses_with_either_suffix() {
+        assert_eq!(PpQuery::parse(&["3x"]).unwrap().misses, Some(3));
+        assert_eq!(PpQuery::parse(&["3m"]).unwrap().misses, Some(3));
+        assert_eq!(PpQuery::parse(&["0X"]).unwrap().misses, Some(0));
+        assert_eq!(
+            PpQuery::parse(&["-1x"]),
+            Err(PpQueryError::Misses("-1x".to_string()))
+        );
+        assert_eq!(
+            PpQuery::parse(&["x"]),
+            Err(PpQueryError::Misses("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_mods_case_insensitively_in_display_order() {
+        let query = PpQuery::parse(&["+dthd"]).unwrap();
+        assert_eq!(acronyms(&query), ["DT", "HD"]);
+        assert_eq!(query.mods.unwrap().mods_string, "HDDT");
+    }
+
+    #[test]
+    fn nomod_clears_the_mods() {
+        for arg in ["+NM", "+nomod"] {
+            let mods = PpQuery::parse(&[arg]).unwrap().mods.unwrap();
+            assert!(mods.mods.is_empty());
+            assert_eq!(mods.mods_string, "NoMod");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_mods() {
+        for arg in ["+", "+HDD", "+XY", "+HDÜ"] {
+            assert_eq!(
+                PpQuery::parse(&[arg]),
+                Err(PpQueryError::Mods(arg.to_string())),
+                "{arg}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_arguments_in_any_order() {
+        let query = PpQuery::parse(&["+HR", "2x", "99"]).unwrap();
+        assert_eq!(query.accuracy, Some(99.0));
+        assert_eq!(query.misses, Some(2));
+        assert_eq!(acronyms(&query), ["HR"]);
+        assert!(query.is_single());
+    }
+
+    #[test]
+    fn mods_alone_ask_for_the_spread() {
+        assert!(!PpQuery::parse(&["+HD"]).unwrap().is_single());
+        assert_eq!(PpQuery::parse(&[]).unwrap(), PpQuery::default());
+    }
+
+    #[test]
+    fn reports_unrecognized_arguments() {
+        let error = PpQuery::parse(&["99", "fc"]).unwrap_err();
+        assert_eq!(error, PpQueryError::Unrecognized("fc".to_string()));
+        assert_eq!(error.argument(), "fc");
+    }
+}
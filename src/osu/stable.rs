@@ -69,7 +69,10 @@ pub async fn run_stable_reader(
                         if beatmap_changed || mods_changed {
                             last_beatmap_id = Some(beatmap.id);
                             *current_beatmap = Some(beatmap.clone());
-                            let _ = tx.send(MemoryEvent::BeatmapChanged(Some(beatmap))).await;
+                            let event = MemoryEvent::BeatmapChanged(Some(beatmap));
+                            let _ = tx.send(event.clone()).await;
+                            // nothing reads the forward channel while Twitch is disconnected
+                            let _ = forward_tx.try_send(event);
                         }
                     }
                     Ok(Err(e)) => {
@@ -83,6 +86,7 @@ pub async fn run_stable_reader(
                             if current_beatmap.is_some() {
                                 *current_beatmap = None;
                                 let _ = tx.send(MemoryEvent::BeatmapChanged(None)).await;
+                                let _ = forward_tx.try_send(MemoryEvent::BeatmapChanged(None));
                                 last_beatmap_id = None;
                             }
                             continue;
@@ -98,8 +102,8 @@ pub async fn run_stable_reader(
 
             Some(cmd) = cmd_rx.next() => {
                 match cmd {
-                    OsuCommand::RequestBeatmapData => {
-                        let event = MemoryEvent::BeatmapDataResponse(current_beatmap.clone());
+                    OsuCommand::RequestBeatmapData(request_id) => {
+                        let event = MemoryEvent::BeatmapDataResponse(request_id, current_beatmap.clone());
                         let _ = tx.send(event.clone()).await;
                         let _ = forward_tx.send(event).await;
                     }
@@ -1,5 +1,26 @@
 use crate::osu::core::BeatmapData;
 use crate::osu::pp::PpValues;
+use crate::responses::StatusNames;
+use crate::text::ellipsize;
+
+pub const PLACEHOLDERS: &[&str] = &[
+    "{artist}",
+    "{title}",
+    "{diff}",
+    "{creator}",
+    "{id}",
+    "{mods}",
+    "{link}",
+    "{status}",
+    "{pp_95}",
+    "{pp_97}",
+    "{pp_98}",
+    "{pp_99}",
+    "{pp_100}",
+];
+const PP_PLACEHOLDERS: &[&str] = &["{pp_95}", "{pp_97}", "{pp_98}", "{pp_99}", "{pp_100}"];
+// names are never shortened below this, so they stay recognizable
+const MIN_SHORTENED_CHARS: usize = 8;
 
 #[derive(Debug, Clone, Default)]
 pub struct Placeholders {
@@ -20,7 +41,7 @@ pub struct Placeholders {
 }
 
 impl Placeholders {
-    pub fn from_beatmap(beatmap: &BeatmapData) -> Self {
+    pub fn from_beatmap(beatmap: &BeatmapData, statuses: &StatusNames) -> Self {
         let mods = beatmap
             .mods
             .as_ref()
@@ -39,7 +60,7 @@ impl Placeholders {
             title: Some(beatmap.title.clone()),
             diff: Some(beatmap.difficulty_name.clone()),
             creator: Some(beatmap.creator.clone()),
-            status: Some(beatmap.status.to_string()),
+            status: Some(statuses.get(beatmap.status).to_string()),
             link: Some(link),
             mods: Some(mods),
             ..Default::default()
@@ -64,54 +85,127 @@ impl Placeholders {
             creator: Some("Creator".to_string()),
             status: Some("Ranked".to_string()),
             link: Some("https://osu.ppy.sh/b/123456".to_string()),
-            mods: Some("+NoMod".to_string()),
-            ..Default::default()
-        }
-    }
-
-    pub fn sample_pp() -> Self {
-        Self {
             mods: Some("+NoMod".to_string()),
             pp_95: Some("350".to_string()),
             pp_97: Some("400".to_string()),
             pp_98: Some("450".to_string()),
             pp_99: Some("500".to_string()),
             pp_100: Some("550".to_string()),
-            ..Default::default()
         }
     }
 
-    fn replace(result: &mut String, placeholder: &str, value: &Option<String>) {
-        if let Some(v) = value {
-            *result = result.replace(placeholder, v);
-        }
+    pub fn uses_pp(template: &str) -> bool {
+        PP_PLACEHOLDERS.iter().any(|p| template.contains(p))
+    }
+
+    fn values(&self) -> [(&'static str, &Option<String>); 13] {
+        [
+            ("{id}", &self.id),
+            ("{artist}", &self.artist),
+            ("{title}", &self.title),
+            ("{diff}", &self.diff),
+            ("{creator}", &self.creator),
+            ("{status}", &self.status),
+            ("{link}", &self.link),
+            ("{mods}", &self.mods),
+            ("{pp_95}", &self.pp_95),
+            ("{pp_97}", &self.pp_97),
+            ("{pp_98}", &self.pp_98),
+            ("{pp_99}", &self.pp_99),
+            ("{pp_100}", &self.pp_100),
+        ]
     }
 
     fn trim(s: String) -> String {
         s.split_whitespace().collect::<Vec<_>>().join(" ")
     }
 
-    pub fn apply_np(&self, format: &str) -> String {
-        let mut result = format.to_string();
-        Self::replace(&mut result, "{id}", &self.id);
-        Self::replace(&mut result, "{artist}", &self.artist);
-        Self::replace(&mut result, "{title}", &self.title);
-        Self::replace(&mut result, "{diff}", &self.diff);
-        Self::replace(&mut result, "{creator}", &self.creator);
-        Self::replace(&mut result, "{status}", &self.status);
-        Self::replace(&mut result, "{link}", &self.link);
-        Self::replace(&mut result, "{mods}", &self.mods);
+    pub fn apply(&self, template: &str) -> String {
+        let mut result = template.to_string();
+        for (placeholder, value) in self.values() {
+            if let Some(v) = value {
+                result = result.replace(placeholder, v);
+            }
+        }
         Self::trim(result)
     }
 
-    pub fn apply_pp(&self, format: &str) -> String {
-        let mut result = format.to_string();
-        Self::replace(&mut result, "{mods}", &self.mods);
-        Self::replace(&mut result, "{pp_95}", &self.pp_95);
-        Self::replace(&mut result, "{pp_97}", &self.pp_97);
-        Self::replace(&mut result, "{pp_98}", &self.pp_98);
-        Self::replace(&mut result, "{pp_99}", &self.pp_99);
-        Self::replace(&mut result, "{pp_100}", &self.pp_100);
-        Self::trim(result)
+    /// Like [`Self::apply`], but shortens the longest names first to fit `max_chars`.
+    pub fn apply_within(&self, template: &str, max_chars: usize) -> String {
+        let mut shortened = self.clone();
+        loop {
+            let result = shortened.apply(template);
+            let excess = result.chars().count().saturating_sub(max_chars);
+            if excess == 0 {
+                return result;
+            }
+
+            let longest = shortened
+                .names_mut(template)
+                .into_iter()
+                .filter(|name| name.chars().count() > MIN_SHORTENED_CHARS)
+                .max_by_key(|name| name.chars().count());
+            let Some(name) = longest else {
+                return ellipsize(&result, max_chars);
+            };
+
+            let keep = name
+                .chars()
+                .count()
+                .saturating_sub(excess)
+                .max(MIN_SHORTENED_CHARS);
+            *name = ellipsize(name, keep);
+        }
+    }
+
+    fn names_mut(&mut self, template: &str) -> Vec<&mut String> {
+        [
+            ("{title}", &mut self.title),
+            ("{artist}", &mut self.artist),
+            ("{diff}", &mut self.diff),
+            ("{creator}", &mut self.creator),
+        ]
+        .into_iter()
+        .filter(|(placeholder, _)| template.contains(placeholder))
+        .filter_map(|(_, value)| value.as_mut())
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn long_title() -> Placeholders {
+        Placeholders {
+            title: Some("A Very Long Title That Goes On".to_string()),
+            artist: Some("Artist".to_string()),
+            ..Placeholders::sample()
+        }
+    }
+
+    #[test]
+    fn leaves_fitting_messages_alone() {
+        let placeholders = Placeholders::sample();
+        assert_eq!(
+            placeholders.apply_within("{artist} - {title}", 50),
+            "Artist - Title"
+        );
+    }
+
+    #[test]
+    fn shortens_the_longest_name_first() {
+        let result = long_title().apply_within("{artist} - {title} {link}", 50);
+        assert!(result.chars().count() <= 50);
+        assert!(result.starts_with("Artist - A Very"));
+        assert!(result.contains("… https"));
+        assert!(result.ends_with("https://osu.ppy.sh/b/123456"));
+    }
+
+    #[test]
+    fn cuts_the_message_once_names_are_short() {
+        let result = long_title().apply_within("{link} {link} {title}", 40);
+        assert_eq!(result.chars().count(), 40);
+        assert!(result.ends_with('…'));
     }
 }
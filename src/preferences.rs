@@ -3,8 +3,16 @@ use std::fs;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::commands::{
+    ChatRole, CustomCommand, DEFAULT_NP_COMMAND, DEFAULT_PP_COMMAND, default_commands,
+    default_ignored_users,
+};
 use crate::log_warn;
-use crate::twitch::{DEFAULT_NP_COMMAND, DEFAULT_NP_FORMAT, DEFAULT_PP_COMMAND, DEFAULT_PP_FORMAT};
+use crate::responses::Responses;
+use crate::twitch::announce::AnnounceSettings;
+use crate::twitch::cooldown::CooldownSettings;
+use crate::twitch::message::LongMessageMode;
+use crate::twitch::shared_chat::SharedChatSettings;
 
 use super::{APP_NAME, VERSION};
 
@@ -18,10 +26,83 @@ pub enum PreferencesError {
 pub struct Config {
     version: String,
     auto_connect: bool,
-    np_command: String,
-    np_format: String,
-    pp_command: String,
-    pp_format: String,
+    #[serde(default)]
+    channel_login: String,
+    #[serde(default = "default_true")]
+    bot_enabled: bool,
+    #[serde(default)]
+    long_messages: LongMessageMode,
+    #[serde(default = "default_ignored_users")]
+    ignored_users: Vec<String>,
+    #[serde(default)]
+    accept_requests: bool,
+    #[serde(default)]
+    request_min_role: ChatRole,
+    #[serde(default)]
+    request_reward_id: String,
+    // superseded by `commands`, only read to migrate older configs
+    #[serde(default, skip_serializing)]
+    np_command: Option<String>,
+    #[serde(default, skip_serializing)]
+    np_format: Option<String>,
+    #[serde(default, skip_serializing)]
+    pp_command: Option<String>,
+    #[serde(default, skip_serializing)]
+    pp_format: Option<String>,
+    #[serde(default)]
+    cooldowns: CooldownSettings,
+    #[serde(default)]
+    announcements: AnnounceSettings,
+    #[serde(default)]
+    shared_chat: SharedChatSettings,
+    #[serde(default)]
+    responses: Responses,
+    #[serde(default = "default_commands")]
+    commands: Vec<CustomCommand>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Config {
+    fn migrate_legacy_commands(&mut self) -> bool {
+        let legacy = [
+            (
+                DEFAULT_NP_COMMAND,
+                self.np_command.take(),
+                self.np_format.take(),
+            ),
+            (
+                DEFAULT_PP_COMMAND,
+                self.pp_command.take(),
+                self.pp_format.take(),
+            ),
+        ];
+
+        let mut migrated = false;
+        for (default_trigger, trigger, template) in legacy {
+            if trigger.is_none() && template.is_none() {
+                continue;
+            }
+            migrated = true;
+
+            let Some(command) = self
+                .commands
+                .iter_mut()
+                .find(|c| c.trigger == default_trigger)
+            else {
+                continue;
+            };
+            if let Some(trigger) = trigger {
+                command.trigger = trigger;
+            }
+            if let Some(template) = template {
+                command.template = template;
+            }
+        }
+        migrated
+    }
 }
 
 impl Default for Config {
@@ -29,10 +110,22 @@ impl Default for Config {
         Self {
             version: VERSION.to_string(),
             auto_connect: false,
-            np_command: DEFAULT_NP_COMMAND.to_string(),
-            np_format: DEFAULT_NP_FORMAT.to_string(),
-            pp_command: DEFAULT_PP_COMMAND.to_string(),
-            pp_format: DEFAULT_PP_FORMAT.to_string(),
+            channel_login: String::new(),
+            bot_enabled: true,
+            long_messages: LongMessageMode::default(),
+            ignored_users: default_ignored_users(),
+            accept_requests: false,
+            request_min_role: ChatRole::default(),
+            request_reward_id: String::new(),
+            np_command: None,
+            np_format: None,
+            pp_command: None,
+            pp_format: None,
+            cooldowns: CooldownSettings::default(),
+            announcements: AnnounceSettings::default(),
+            shared_chat: SharedChatSettings::default(),
+            responses: Responses::default(),
+            commands: default_commands(),
         }
     }
 }
@@ -43,8 +136,14 @@ pub struct PreferencesStore {
 
 impl PreferencesStore {
     fn load() -> Result<Self, PreferencesError> {
-        let config: Config = confy::load(APP_NAME, None)?;
-        Ok(Self { config })
+        let mut config: Config = confy::load(APP_NAME, None)?;
+        let migrated = config.migrate_legacy_commands();
+
+        let store = Self { config };
+        if migrated && let Err(e) = store.save() {
+            log_warn!("prefs", "Failed to save migrated commands: {e}");
+        }
+        Ok(store)
     }
 
     pub fn load_or_default() -> Self {
@@ -86,20 +185,52 @@ impl PreferencesStore {
         self.config.auto_connect
     }
 
-    pub fn np_command(&self) -> &str {
-        &self.config.np_command
+    pub fn commands(&self) -> &[CustomCommand] {
+        &self.config.commands
+    }
+
+    pub fn channel_login(&self) -> &str {
+        &self.config.channel_login
     }
 
-    pub fn np_format(&self) -> &str {
-        &self.config.np_format
+    pub fn bot_enabled(&self) -> bool {
+        self.config.bot_enabled
     }
 
-    pub fn pp_command(&self) -> &str {
-        &self.config.pp_command
+    pub fn long_messages(&self) -> LongMessageMode {
+        self.config.long_messages
     }
 
-    pub fn pp_format(&self) -> &str {
-        &self.config.pp_format
+    pub fn ignored_users(&self) -> &[String] {
+        &self.config.ignored_users
+    }
+
+    pub fn accept_requests(&self) -> bool {
+        self.config.accept_requests
+    }
+
+    pub fn request_min_role(&self) -> ChatRole {
+        self.config.request_min_role
+    }
+
+    pub fn request_reward_id(&self) -> &str {
+        &self.config.request_reward_id
+    }
+
+    pub fn cooldowns(&self) -> &CooldownSettings {
+        &self.config.cooldowns
+    }
+
+    pub fn announcements(&self) -> &AnnounceSettings {
+        &self.config.announcements
+    }
+
+    pub fn shared_chat(&self) -> &SharedChatSettings {
+        &self.config.shared_chat
+    }
+
+    pub fn responses(&self) -> &Responses {
+        &self.config.responses
     }
 
     pub fn set_auto_connect(value: bool) -> Result<(), PreferencesError> {
@@ -108,27 +239,75 @@ impl PreferencesStore {
         store.save()
     }
 
-    pub fn set_np_command(value: String) -> Result<(), PreferencesError> {
+    pub fn set_channel_login(value: String) -> Result<(), PreferencesError> {
+        let mut store = Self::load_or_default();
+        store.config.channel_login = value;
+        store.save()
+    }
+
+    pub fn set_bot_enabled(value: bool) -> Result<(), PreferencesError> {
+        let mut store = Self::load_or_default();
+        store.config.bot_enabled = value;
+        store.save()
+    }
+
+    pub fn set_long_messages(value: LongMessageMode) -> Result<(), PreferencesError> {
+        let mut store = Self::load_or_default();
+        store.config.long_messages = value;
+        store.save()
+    }
+
+    pub fn set_ignored_users(value: Vec<String>) -> Result<(), PreferencesError> {
+        let mut store = Self::load_or_default();
+        store.config.ignored_users = value;
+        store.save()
+    }
+
+    pub fn set_accept_requests(value: bool) -> Result<(), PreferencesError> {
+        let mut store = Self::load_or_default();
+        store.config.accept_requests = value;
+        store.save()
+    }
+
+    pub fn set_request_min_role(value: ChatRole) -> Result<(), PreferencesError> {
+        let mut store = Self::load_or_default();
+        store.config.request_min_role = value;
+        store.save()
+    }
+
+    pub fn set_request_reward_id(value: String) -> Result<(), PreferencesError> {
+        let mut store = Self::load_or_default();
+        store.config.request_reward_id = value;
+        store.save()
+    }
+
+    pub fn set_commands(value: Vec<CustomCommand>) -> Result<(), PreferencesError> {
+        let mut store = Self::load_or_default();
+        store.config.commands = value;
+        store.save()
+    }
+
+    pub fn set_cooldowns(value: CooldownSettings) -> Result<(), PreferencesError> {
         let mut store = Self::load_or_default();
-        store.config.np_command = value;
+        store.config.cooldowns = value;
         store.save()
     }
 
-    pub fn set_np_format(value: String) -> Result<(), PreferencesError> {
+    pub fn set_announcements(value: AnnounceSettings) -> Result<(), PreferencesError> {
         let mut store = Self::load_or_default();
-        store.config.np_format = value;
+        store.config.announcements = value;
         store.save()
     }
 
-    pub fn set_pp_command(value: String) -> Result<(), PreferencesError> {
+    pub fn set_shared_chat(value: SharedChatSettings) -> Result<(), PreferencesError> {
         let mut store = Self::load_or_default();
-        store.config.pp_command = value;
+        store.config.shared_chat = value;
         store.save()
     }
 
-    pub fn set_pp_format(value: String) -> Result<(), PreferencesError> {
+    pub fn set_responses(value: Responses) -> Result<(), PreferencesError> {
         let mut store = Self::load_or_default();
-        store.config.pp_format = value;
+        store.config.responses = value;
         store.save()
     }
 }
@@ -0,0 +1,184 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+static QUEUE: OnceLock<Mutex<Vec<BeatmapRequest>>> = OnceLock::new();
+static ACCEPTING: AtomicBool = AtomicBool::new(false);
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestSource {
+    Chat {
+        message_id: String,
+    },
+    Redemption {
+        reward_id: String,
+        redemption_id: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BeatmapRequest {
+    pub id: u64,
+    pub beatmap_id: u32,
+    pub requester: String,
+    pub source: RequestSource,
+}
+
+impl BeatmapRequest {
+    pub fn web_link(&self) -> String {
+        format!("https://osu.ppy.sh/b/{}", self.beatmap_id)
+    }
+
+    pub fn osu_link(&self) -> String {
+        format!("osu://b/{}", self.beatmap_id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Enqueued {
+    Added(usize),
+    AlreadyQueued(usize),
+}
+
+/// Difficulty id from an osu! beatmap link. Links to a whole set are ignored.
+pub fn parse_beatmap_link(token: &str) -> Option<u32> {
+    let (_, path) = token.split_once("osu.ppy.sh/")?;
+    let (kind, rest) = path.split_once('/')?;
+
+    match kind {
+        "b" | "beatmaps" => leading_number(rest),
+        "beatmapsets" => {
+            let (_, fragment) = rest.split_once('#')?;
+            let (_, id) = fragment.split_once('/')?;
+            leading_number(id)
+        }
+        _ => None,
+    }
+}
+
+fn leading_number(s: &str) -> Option<u32> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s[..end].parse().ok()
+}
+
+pub fn parse_beatmap_input(input: &str) -> Option<u32> {
+    find_beatmap_link(input).or_else(|| input.trim().parse().ok())
+}
+
+pub fn find_beatmap_link(text: &str) -> Option<u32> {
+    text.split_whitespace().find_map(parse_beatmap_link)
+}
+
+fn queue() -> &'static Mutex<Vec<BeatmapRequest>> {
+    QUEUE.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+pub fn is_accepting() -> bool {
+    ACCEPTING.load(Ordering::Relaxed)
+}
+
+pub fn set_accepting(value: bool) {
+    ACCEPTING.store(value, Ordering::Relaxed);
+}
+
+/// Adds a request unless the beatmap is already queued. Positions are 1-based.
+pub fn enqueue(beatmap_id: u32, requester: &str, source: RequestSource) -> Enqueued {
+    let mut queue = queue().lock().unwrap();
+
+    if let Some(index) = queue.iter().position(|r| r.beatmap_id == beatmap_id) {
+        return Enqueued::AlreadyQueued(index + 1);
+    }
+
+    queue.push(BeatmapRequest {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        beatmap_id,
+        requester: requester.to_string(),
+        source,
+    });
+    Enqueued::Added(queue.len())
+}
+
+pub fn preview(beatmap_id: u32) -> Enqueued {
+    let queue = queue().lock().unwrap();
+    match queue.iter().position(|r| r.beatmap_id == beatmap_id) {
+        Some(index) => Enqueued::AlreadyQueued(index + 1),
+        None => Enqueued::Added(queue.len() + 1),
+    }
+}
+
+pub fn remove(id: u64) -> Option<BeatmapRequest> {
+    let mut queue = queue().lock().unwrap();
+    let index = queue.iter().position(|r| r.id == id)?;
+    Some(queue.remove(index))
+}
+
+pub fn snapshot() -> Vec<BeatmapRequest> {
+    queue().lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn previews_without_queueing() {
+        let beatmap_id = u32::MAX;
+        assert!(matches!(preview(beatmap_id), Enqueued::Added(_)));
+        assert!(snapshot().iter().all(|r| r.beatmap_id != beatmap_id));
+
+        let position = match enqueue(
+            beatmap_id,
+            "someone",
+            RequestSource::Chat {
+                message_id: String::new(),
+            },
+        ) {
+            Enqueued::Added(position) => position,
+            Enqueued::AlreadyQueued(position) => position,
+        };
+        assert_eq!(preview(beatmap_id), Enqueued::AlreadyQueued(position));
+    }
+
+    #[test]
+    fn parses_difficulty_links() {
+        assert_eq!(
+            parse_beatmap_link("https://osu.ppy.sh/b/129891"),
+            Some(129891)
+        );
+        assert_eq!(
+            parse_beatmap_link("https://osu.ppy.sh/beatmaps/129891?mode=osu"),
+            Some(129891)
+        );
+        assert_eq!(
+            parse_beatmap_link("osu.ppy.sh/beatmapsets/39804#osu/129891"),
+            Some(129891)
+        );
+    }
+
+    #[test]
+    fn ignores_links_without_a_difficulty() {
+        assert_eq!(
+            parse_beatmap_link("https://osu.ppy.sh/beatmapsets/39804"),
+            None
+        );
+        assert_eq!(parse_beatmap_link("https://osu.ppy.sh/users/2"), None);
+        assert_eq!(parse_beatmap_link("https://osu.ppy.sh/b/"), None);
+        assert_eq!(parse_beatmap_link("https://example.com/b/129891"), None);
+    }
+
+    #[test]
+    fn finds_links_within_text() {
+        assert_eq!(
+            find_beatmap_link("play this https://osu.ppy.sh/b/129891 pls"),
+            Some(129891)
+        );
+        assert_eq!(find_beatmap_link("no links here"), None);
+    }
+
+    #[test]
+    fn accepts_bare_ids_as_input() {
+        assert_eq!(parse_beatmap_input(" 129891 "), Some(129891));
+        assert_eq!(parse_beatmap_input("osu.ppy.sh/b/129891"), Some(129891));
+        assert_eq!(parse_beatmap_input("freedom dive"), None);
+    }
+}
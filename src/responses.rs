@@ -0,0 +1,374 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::osu::core::BeatmapStatus;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    German,
+    Japanese,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::English, Language::German, Language::Japanese];
+
+    pub fn responses(self) -> Responses {
+        match self {
+            Language::English => Responses::default(),
+            Language::German => german(),
+            Language::Japanese => japanese(),
+        }
+    }
+}
+
+impl Display for Language {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Language::English => write!(f, "English"),
+            Language::German => write!(f, "German"),
+            Language::Japanese => write!(f, "Japanese"),
+        }
+    }
+}
+
+/// Messages the bot sends on its own. Entries missing from the config fall back to English.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Responses {
+    pub no_beatmap: String,
+    pub pp_unavailable: String,
+    pub osu_timed_out: String,
+    pub pp_query_result: String,
+    pub invalid_pp_argument: String,
+    pub on_cooldown: String,
+    pub request_added: String,
+    pub request_already_queued: String,
+    pub request_approved: String,
+    pub redemption_closed: String,
+    pub redemption_invalid: String,
+    pub redemption_already_queued: String,
+    pub bot_enabled: String,
+    pub bot_disabled: String,
+    pub cooldown_set: String,
+    pub format_set: String,
+    pub control_usage: String,
+    pub set_format_usage: String,
+    pub statuses: StatusNames,
+}
+
+impl Default for Responses {
+    fn default() -> Self {
+        Self {
+            no_beatmap: "No beatmap currently selected".to_string(),
+            pp_unavailable: "pp calculation currently not available".to_string(),
+            osu_timed_out: "osu! didn't respond in time, try again".to_string(),
+            pp_query_result: "{query}: {pp}pp".to_string(),
+            invalid_pp_argument: "{argument} isn't a valid argument. Usage: {trigger} [accuracy] [misses]x [+mods], e.g. {trigger} 99 1x +HDDT".to_string(),
+            on_cooldown: "{trigger} is on cooldown, try again in {seconds}s".to_string(),
+            request_added: "Request added to the queue at position {position}".to_string(),
+            request_already_queued: "That beatmap is already in the queue at position {position}"
+                .to_string(),
+            request_approved: "Your request was approved and is up next".to_string(),
+            redemption_closed: "Requests are currently closed, your points were refunded"
+                .to_string(),
+            redemption_invalid:
+                "Couldn't find a beatmap link in your redemption, your points were refunded"
+                    .to_string(),
+            redemption_already_queued: "That beatmap is already in the queue at position {position}, your points were refunded".to_string(),
+            bot_enabled: "The bot is now on".to_string(),
+            bot_disabled: "The bot is now off".to_string(),
+            cooldown_set: "Command cooldown set to {seconds}s".to_string(),
+            format_set: "Updated the format of {trigger}".to_string(),
+            control_usage: "Usage: {trigger} on | off | cooldown <seconds>".to_string(),
+            set_format_usage: "Usage: {trigger} <format>".to_string(),
+            statuses: StatusNames::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseField {
+    NoBeatmap,
+    PpUnavailable,
+    OsuTimedOut,
+    PpQueryResult,
+    InvalidPpArgument,
+    OnCooldown,
+    RequestAdded,
+    RequestAlreadyQueued,
+    RequestApproved,
+    RedemptionClosed,
+    RedemptionInvalid,
+    RedemptionAlreadyQueued,
+    BotEnabled,
+    BotDisabled,
+    CooldownSet,
+    FormatSet,
+    ControlUsage,
+    SetFormatUsage,
+}
+
+impl ResponseField {
+    pub const ALL: [ResponseField; 18] = [
+        ResponseField::NoBeatmap,
+        ResponseField::PpUnavailable,
+        ResponseField::OsuTimedOut,
+        ResponseField::PpQueryResult,
+        ResponseField::InvalidPpArgument,
+        ResponseField::OnCooldown,
+        ResponseField::RequestAdded,
+        ResponseField::RequestAlreadyQueued,
+        ResponseField::RequestApproved,
+        ResponseField::RedemptionClosed,
+        ResponseField::RedemptionInvalid,
+        ResponseField::RedemptionAlreadyQueued,
+        ResponseField::BotEnabled,
+        ResponseField::BotDisabled,
+        ResponseField::CooldownSet,
+        ResponseField::FormatSet,
+        ResponseField::ControlUsage,
+        ResponseField::SetFormatUsage,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ResponseField::NoBeatmap => "No beatmap",
+            ResponseField::PpUnavailable => "No pp",
+            ResponseField::OsuTimedOut => "osu! not responding",
+            ResponseField::PpQueryResult => "pp for arguments",
+            ResponseField::InvalidPpArgument => "Invalid pp argument",
+            ResponseField::OnCooldown => "On cooldown",
+            ResponseField::RequestAdded => "Request added",
+            ResponseField::RequestAlreadyQueued => "Already requested",
+            ResponseField::RequestApproved => "Request approved",
+            ResponseField::RedemptionClosed => "Redemption, closed",
+            ResponseField::RedemptionInvalid => "Redemption, no link",
+            ResponseField::RedemptionAlreadyQueued => "Redemption, already requested",
+            ResponseField::BotEnabled => "Bot turned on",
+            ResponseField::BotDisabled => "Bot turned off",
+            ResponseField::CooldownSet => "Cooldown changed",
+            ResponseField::FormatSet => "Format changed",
+            ResponseField::ControlUsage => "Bot command usage",
+            ResponseField::SetFormatUsage => "Format command usage",
+        }
+    }
+}
+
+impl Responses {
+    pub fn get(&self, field: ResponseField) -> &str {
+        match field {
+            ResponseField::NoBeatmap => &self.no_beatmap,
+            ResponseField::PpUnavailable => &self.pp_unavailable,
+            ResponseField::OsuTimedOut => &self.osu_timed_out,
+            ResponseField::PpQueryResult => &self.pp_query_result,
+            ResponseField::InvalidPpArgument => &self.invalid_pp_argument,
+            ResponseField::OnCooldown => &self.on_cooldown,
+            ResponseField::RequestAdded => &self.request_added,
+            ResponseField::RequestAlreadyQueued => &self.request_already_queued,
+            ResponseField::RequestApproved => &self.request_approved,
+            ResponseField::RedemptionClosed => &self.redemption_closed,
+            ResponseField::RedemptionInvalid => &self.redemption_invalid,
+            ResponseField::RedemptionAlreadyQueued => &self.redemption_already_queued,
+            ResponseField::BotEnabled => &self.bot_enabled,
+            ResponseField::BotDisabled => &self.bot_disabled,
+            ResponseField::CooldownSet => &self.cooldown_set,
+            ResponseField::FormatSet => &self.format_set,
+            ResponseField::ControlUsage => &self.control_usage,
+            ResponseField::SetFormatUsage => &self.set_format_usage,
+        }
+    }
+
+    pub fn set(&mut self, field: ResponseField, value: String) {
+        let target = match field {
+            ResponseField::NoBeatmap => &mut self.no_beatmap,
+            ResponseField::PpUnavailable => &mut self.pp_unavailable,
+            ResponseField::OsuTimedOut => &mut self.osu_timed_out,
+            ResponseField::PpQueryResult => &mut self.pp_query_result,
+            ResponseField::InvalidPpArgument => &mut self.invalid_pp_argument,
+            ResponseField::OnCooldown => &mut self.on_cooldown,
+            ResponseField::RequestAdded => &mut self.request_added,
+            ResponseField::RequestAlreadyQueued => &mut self.request_already_queued,
+            ResponseField::RequestApproved => &mut self.request_approved,
+            ResponseField::RedemptionClosed => &mut self.redemption_closed,
+            ResponseField::RedemptionInvalid => &mut self.redemption_invalid,
+            ResponseField::RedemptionAlreadyQueued => &mut self.redemption_already_queued,
+            ResponseField::BotEnabled => &mut self.bot_enabled,
+            ResponseField::BotDisabled => &mut self.bot_disabled,
+            ResponseField::CooldownSet => &mut self.cooldown_set,
+            ResponseField::FormatSet => &mut self.format_set,
+            ResponseField::ControlUsage => &mut self.control_usage,
+            ResponseField::SetFormatUsage => &mut self.set_format_usage,
+        };
+        *target = value;
+    }
+
+    pub fn render(&self, field: ResponseField, values: &[(&str, &str)]) -> String {
+        let mut result = self.get(field).to_string();
+        for (name, value) in values {
+            result = result.replace(&format!("{{{}}}", name), value);
+        }
+        result
+    }
+
+    pub fn language(&self) -> Option<Language> {
+        Language::ALL
+            .into_iter()
+            .find(|language| language.responses() == *self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusNames {
+    pub unknown: String,
+    pub not_submitted: String,
+    pub wip: String,
+    pub pending: String,
+    pub ranked: String,
+    pub approved: String,
+    pub qualified: String,
+    pub loved: String,
+    pub graveyard: String,
+    pub stable_pending: String,
+}
+
+impl Default for StatusNames {
+    fn default() -> Self {
+        Self::from_fn(|status| status.to_string())
+    }
+}
+
+impl StatusNames {
+    fn from_fn(name: impl Fn(BeatmapStatus) -> String) -> Self {
+        Self {
+            unknown: name(BeatmapStatus::Unknown),
+            not_submitted: name(BeatmapStatus::NotSubmitted),
+            wip: name(BeatmapStatus::Wip),
+            pending: name(BeatmapStatus::Pending),
+            ranked: name(BeatmapStatus::Ranked),
+            approved: name(BeatmapStatus::Approved),
+            qualified: name(BeatmapStatus::Qualified),
+            loved: name(BeatmapStatus::Loved),
+            graveyard: name(BeatmapStatus::Graveyard),
+            stable_pending: name(BeatmapStatus::StablePending),
+        }
+    }
+
+    pub fn get(&self, status: BeatmapStatus) -> &str {
+        match status {
+            BeatmapStatus::Unknown => &self.unknown,
+            BeatmapStatus::NotSubmitted => &self.not_submitted,
+            BeatmapStatus::Wip => &self.wip,
+            BeatmapStatus::Pending => &self.pending,
+            BeatmapStatus::Ranked => &self.ranked,
+            BeatmapStatus::Approved => &self.approved,
+            BeatmapStatus::Qualified => &self.qualified,
+            BeatmapStatus::Loved => &self.loved,
+            BeatmapStatus::Graveyard => &self.graveyard,
+            BeatmapStatus::StablePending => &self.stable_pending,
+        }
+    }
+
+    pub fn set(&mut self, status: BeatmapStatus, value: String) {
+        let target = match status {
+            BeatmapStatus::Unknown => &mut self.unknown,
+            BeatmapStatus::NotSubmitted => &mut self.not_submitted,
+            BeatmapStatus::Wip => &mut self.wip,
+            BeatmapStatus::Pending => &mut self.pending,
+            BeatmapStatus::Ranked => &mut self.ranked,
+            BeatmapStatus::Approved => &mut self.approved,
+            BeatmapStatus::Qualified => &mut self.qualified,
+            BeatmapStatus::Loved => &mut self.loved,
+            BeatmapStatus::Graveyard => &mut self.graveyard,
+            BeatmapStatus::StablePending => &mut self.stable_pending,
+        };
+        *target = value;
+    }
+}
+
+fn german() -> Responses {
+    Responses {
+        no_beatmap: "Gerade ist keine Beatmap ausgewählt".to_string(),
+        pp_unavailable: "pp-Berechnung gerade nicht verfügbar".to_string(),
+        osu_timed_out: "osu! hat nicht rechtzeitig geantwortet, versuch es noch mal".to_string(),
+        pp_query_result: "Mit {query}: {pp}pp".to_string(),
+        invalid_pp_argument: "{argument} ist kein gültiges Argument. Verwendung: {trigger} [Genauigkeit] [Misses]x [+Mods], z. B. {trigger} 99 1x +HDDT".to_string(),
+        on_cooldown: "{trigger} hat gerade Cooldown, versuch es in {seconds}s noch mal".to_string(),
+        request_added: "Request auf Platz {position} der Warteschlange hinzugefügt".to_string(),
+        request_already_queued: "Diese Beatmap ist schon auf Platz {position} der Warteschlange"
+            .to_string(),
+        request_approved: "Dein Request wurde angenommen und ist als Nächstes dran".to_string(),
+        redemption_closed: "Requests sind gerade geschlossen, deine Punkte wurden zurückerstattet"
+            .to_string(),
+        redemption_invalid:
+            "In deiner Einlösung war kein Beatmap-Link, deine Punkte wurden zurückerstattet"
+                .to_string(),
+        redemption_already_queued: "Diese Beatmap ist schon auf Platz {position} der Warteschlange, deine Punkte wurden zurückerstattet".to_string(),
+        bot_enabled: "Der Bot ist jetzt an".to_string(),
+        bot_disabled: "Der Bot ist jetzt aus".to_string(),
+        cooldown_set: "Befehls-Cooldown auf {seconds}s gesetzt".to_string(),
+        format_set: "Format von {trigger} aktualisiert".to_string(),
+        control_usage: "Verwendung: {trigger} on | off | cooldown <Sekunden>".to_string(),
+        set_format_usage: "Verwendung: {trigger} <Format>".to_string(),
+        statuses: StatusNames::from_fn(|status| {
+            match status {
+                BeatmapStatus::Unknown => "Unbekannt",
+                BeatmapStatus::NotSubmitted => "Lokal/Nicht hochgeladen",
+                BeatmapStatus::Wip => "WIP",
+                BeatmapStatus::Pending => "Ausstehend",
+                BeatmapStatus::Ranked => "Ranked",
+                BeatmapStatus::Approved => "Approved",
+                BeatmapStatus::Qualified => "Qualifiziert",
+                BeatmapStatus::Loved => "Loved",
+                BeatmapStatus::Graveyard => "Friedhof",
+                BeatmapStatus::StablePending => "Ausstehend/Friedhof",
+            }
+            .to_string()
+        }),
+    }
+}
+
+fn japanese() -> Responses {
+    Responses {
+        no_beatmap: "現在選択されている譜面はありません".to_string(),
+        pp_unavailable: "現在pp計算は利用できません".to_string(),
+        osu_timed_out: "osu!から応答がありませんでした。もう一度お試しください".to_string(),
+        pp_query_result: "{query} の場合: {pp}pp".to_string(),
+        invalid_pp_argument: "{argument} は無効な引数です。使い方: {trigger} [精度] [ミス数]x [+Mod] 例: {trigger} 99 1x +HDDT".to_string(),
+        on_cooldown: "{trigger} はクールダウン中です。{seconds}秒後にもう一度お試しください"
+            .to_string(),
+        request_added: "リクエストをキューの{position}番目に追加しました".to_string(),
+        request_already_queued: "その譜面はすでにキューの{position}番目にあります".to_string(),
+        request_approved: "リクエストが承認されました。次にプレイします".to_string(),
+        redemption_closed: "現在リクエストは受け付けていません。ポイントは返還されました"
+            .to_string(),
+        redemption_invalid:
+            "引き換えに譜面のリンクが見つかりませんでした。ポイントは返還されました".to_string(),
+        redemption_already_queued:
+            "その譜面はすでにキューの{position}番目にあります。ポイントは返還されました"
+                .to_string(),
+        bot_enabled: "ボットをオンにしました".to_string(),
+        bot_disabled: "ボットをオフにしました".to_string(),
+        cooldown_set: "コマンドのクールダウンを{seconds}秒に設定しました".to_string(),
+        format_set: "{trigger} のフォーマットを更新しました".to_string(),
+        control_usage: "使い方: {trigger} on | off | cooldown <秒数>".to_string(),
+        set_format_usage: "使い方: {trigger} <フォーマット>".to_string(),
+        statuses: StatusNames::from_fn(|status| {
+            match status {
+                BeatmapStatus::Unknown => "不明",
+                BeatmapStatus::NotSubmitted => "ローカル/未投稿",
+                BeatmapStatus::Wip => "WIP",
+                BeatmapStatus::Pending => "Pending",
+                BeatmapStatus::Ranked => "Ranked",
+                BeatmapStatus::Approved => "Approved",
+                BeatmapStatus::Qualified => "Qualified",
+                BeatmapStatus::Loved => "Loved",
+                BeatmapStatus::Graveyard => "Graveyard",
+                BeatmapStatus::StablePending => "Pending/Graveyard",
+            }
+            .to_string()
+        }),
+    }
+}
@@ -0,0 +1,34 @@
+const ELLIPSIS: char = '…';
+
+pub fn truncate_chars(s: &str, max_chars: usize) -> &str {
+    match s.char_indices().nth(max_chars) {
+        Some((end, _)) => &s[..end],
+        None => s,
+    }
+}
+
+pub fn ellipsize(s: &str, max_chars: usize) -> String {
+    if s.chars().count() <= max_chars {
+        return s.to_string();
+    }
+    let kept = truncate_chars(s, max_chars.saturating_sub(1)).trim_end();
+    format!("{}{}", kept, ELLIPSIS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncates_on_character_boundaries() {
+        assert_eq!(truncate_chars("日本語です", 3), "日本語");
+        assert_eq!(truncate_chars("short", 10), "short");
+    }
+
+    #[test]
+    fn ellipsizes_only_what_is_too_long() {
+        assert_eq!(ellipsize("Freedom Dive", 12), "Freedom Dive");
+        assert_eq!(ellipsize("Freedom Dive", 9), "Freedom…");
+        assert_eq!(ellipsize("Freedom Dive", 8).chars().count(), 8);
+    }
+}
@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+use tokio::time::{Duration, Instant};
+
+use crate::osu::core::BeatmapData;
+
+pub const DEFAULT_ANNOUNCE_FORMAT: &str =
+    "Now playing: {artist} - {title} [{diff}] ({creator}) {mods} {link}";
+pub const DEFAULT_ANNOUNCE_DELAY_SECONDS: u64 = 5;
+pub const DEFAULT_ANNOUNCE_INTERVAL_SECONDS: u64 = 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnounceSettings {
+    pub enabled: bool,
+    pub template: String,
+    pub delay_seconds: u64,
+    pub min_interval_seconds: u64,
+}
+
+impl Default for AnnounceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            template: DEFAULT_ANNOUNCE_FORMAT.to_string(),
+            delay_seconds: DEFAULT_ANNOUNCE_DELAY_SECONDS,
+            min_interval_seconds: DEFAULT_ANNOUNCE_INTERVAL_SECONDS,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Announcer {
+    pending: Option<(BeatmapData, Instant)>,
+    last_beatmap_id: Option<i32>,
+    last_sent: Option<Instant>,
+}
+
+impl Announcer {
+    pub fn beatmap_changed(
+        &mut self,
+        settings: &AnnounceSettings,
+        beatmap: Option<&BeatmapData>,
+        now: Instant,
+    ) {
+        let Some(beatmap) = beatmap.filter(|b| settings.enabled && b.is_playing() && b.id > 0)
+        else {
+            self.pending = None;
+            return;
+        };
+
+        if self.last_beatmap_id == Some(beatmap.id) {
+            self.pending = None;
+        } else if self.pending.as_ref().map(|(b, _)| b.id) != Some(beatmap.id) {
+            let at = now + Duration::from_secs(settings.delay_seconds);
+            self.pending = Some((beatmap.clone(), at));
+        }
+    }
+
+    pub fn deadline(&self, settings: &AnnounceSettings) -> Option<Instant> {
+        let (_, at) = self.pending.as_ref()?;
+        let earliest = self
+            .last_sent
+            .map(|last| last + Duration::from_secs(settings.min_interval_seconds));
+        Some(earliest.map_or(*at, |earliest| earliest.max(*at)))
+    }
+
+    pub fn take_due(&mut self, settings: &AnnounceSettings, now: Instant) -> Option<BeatmapData> {
+        if self.deadline(settings)? > now {
+            return None;
+        }
+
+        let (beatmap, _) = self.pending.take()?;
+        self.last_beatmap_id = Some(beatmap.id);
+        self.last_sent = Some(now);
+        Some(beatmap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osu::core::{BeatmapStatus, GameplayMods};
+
+    fn settings() -> AnnounceSettings {
+        AnnounceSettings {
+            enabled: true,
+            delay_seconds: 5,
+            min_interval_seconds: 60,
+            ..Default::default()
+        }
+    }
+
+    fn playing(id: i32) -> BeatmapData {
+        BeatmapData {
+            id,
+            artist: "Artist".to_string(),
+            title: "Title".to_string(),
+            difficulty_name: "Insane".to_string(),
+            creator: "Mapper".to_string(),
+            status: BeatmapStatus::Ranked,
+            mods: Some(GameplayMods::default()),
+            osu_file_path: None,
+            songs_folder: None,
+        }
+    }
+
+    fn seconds(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn waits_for_the_map_to_be_played_for_a_while() {
+        let settings = settings();
+        let now = Instant::now();
+        let mut announcer = Announcer::default();
+
+        announcer.beatmap_changed(&settings, Some(&playing(1)), now);
+        assert_eq!(announcer.deadline(&settings), Some(now + seconds(5)));
+        assert!(announcer.take_due(&settings, now + seconds(4)).is_none());
+
+        // quitting out before then cancels it
+        announcer.beatmap_changed(&settings, None, now + seconds(4));
+        assert!(announcer.take_due(&settings, now + seconds(5)).is_none());
+    }
+
+    #[test]
+    fn announces_the_same_map_only_once() {
+        let settings = settings();
+        let now = Instant::now();
+        let mut announcer = Announcer::default();
+
+        announcer.beatmap_changed(&settings, Some(&playing(1)), now);
+        assert_eq!(
+            announcer
+                .take_due(&settings, now + seconds(5))
+                .map(|beatmap| beatmap.id),
+            Some(1)
+        );
+
+        // a retry much later still isn't announced again
+        announcer.beatmap_changed(&settings, Some(&playing(1)), now + seconds(600));
+        assert_eq!(announcer.deadline(&settings), None);
+    }
+
+    #[test]
+    fn keeps_the_minimum_interval() {
+        let settings = settings();
+        let now = Instant::now();
+        let mut announcer = Announcer::default();
+
+        announcer.beatmap_changed(&settings, Some(&playing(1)), now);
+        announcer.take_due(&settings, now + seconds(5));
+
+        announcer.beatmap_changed(&settings, Some(&playing(2)), now + seconds(10));
+        assert_eq!(announcer.deadline(&settings), Some(now + seconds(65)));
+        assert!(announcer.take_due(&settings, now + seconds(64)).is_none());
+        assert!(announcer.take_due(&settings, now + seconds(65)).is_some());
+    }
+}
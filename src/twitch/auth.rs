@@ -0,0 +1,287 @@
+use iced::futures::SinkExt;
+use iced::futures::channel::mpsc;
+use serde::Deserialize;
+use tokio::time::{self, Duration, Instant};
+
+use super::core::{BoxError, TwitchError, TwitchEvent};
+use super::endpoints::endpoints;
+use crate::commands::DeliveryMode;
+use crate::credentials::{CredentialError, CredentialStore};
+use crate::{log_debug, log_error, log_info};
+
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+pub const REQUIRED_SCOPES: &[&str] = &["channel:bot", "user:read:chat", "user:write:chat"];
+pub const REDEMPTIONS_SCOPE: &str = "channel:manage:redemptions";
+pub const ANNOUNCEMENTS_SCOPE: &str = "moderator:manage:announcements";
+pub const WHISPERS_SCOPE: &str = "user:manage:whispers";
+pub const VALIDATION_INTERVAL_SECONDS: u64 = 3600;
+const EXPIRY_WARNING_SECONDS: u64 = 3 * 24 * 3600;
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct TokenInfo {
+    pub client_id: String,
+    pub login: Option<String>,
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    pub expires_in: u64,
+    #[serde(skip)]
+    pub refreshable: bool,
+}
+
+impl TokenInfo {
+    pub fn missing_scopes(&self) -> Vec<String> {
+        REQUIRED_SCOPES
+            .iter()
+            .filter(|scope| !self.scopes.iter().any(|s| s == *scope))
+            .map(|scope| scope.to_string())
+            .collect()
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+
+    pub fn expires_soon(&self) -> bool {
+        !self.refreshable && self.expires_in != 0 && self.expires_in <= EXPIRY_WARNING_SECONDS
+    }
+}
+
+pub fn delivery_scope(mode: DeliveryMode) -> Option<&'static str> {
+    match mode {
+        DeliveryMode::Reply | DeliveryMode::Message => None,
+        DeliveryMode::Announcement => Some(ANNOUNCEMENTS_SCOPE),
+        DeliveryMode::Whisper => Some(WHISPERS_SCOPE),
+    }
+}
+
+pub fn login_scopes() -> Vec<&'static str> {
+    let mut scopes = REQUIRED_SCOPES.to_vec();
+    scopes.extend([REDEMPTIONS_SCOPE, ANNOUNCEMENTS_SCOPE, WHISPERS_SCOPE]);
+    scopes
+}
+
+pub fn format_expiry(seconds: u64) -> String {
+    let days = seconds / 86400;
+    let hours = (seconds % 86400) / 3600;
+    let minutes = (seconds % 3600) / 60;
+
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+/// Twitch expects apps to validate their token on startup and hourly afterwards.
+pub async fn validate_token(
+    http_client: &reqwest::Client,
+    client_id: &str,
+    access_token: &str,
+) -> Result<TokenInfo, BoxError> {
+    log_debug!("twitch-auth", "Validating access token");
+    let response = http_client
+        .get(endpoints().auth("validate"))
+        .header("Authorization", format!("OAuth {}", access_token))
+        .send()
+        .await?;
+
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        let error_text = response.text().await?;
+        log_debug!("twitch-auth", "Token validation failed: {}", error_text);
+        return Err(TwitchError::Unauthorized("Invalid or expired token".to_string()).into());
+    }
+
+    let mut info: TokenInfo = response.error_for_status()?.json().await?;
+    info.refreshable = CredentialStore::load_refresh_token().is_ok();
+
+    if info.client_id != client_id {
+        return Err(TwitchError::Unauthorized(
+            "Token was issued for a different application".to_string(),
+        )
+        .into());
+    }
+
+    log_debug!(
+        "twitch-auth",
+        "Token valid for {:?}, scopes: {:?}, expires in {}s",
+        info.login,
+        info.scopes,
+        info.expires_in
+    );
+    Ok(info)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceCode {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub expires_in: u64,
+    pub interval: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_in: u64,
+    #[serde(default)]
+    pub scope: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OAuthErrorResponse {
+    message: String,
+}
+
+pub async fn request_device_code(
+    http_client: &reqwest::Client,
+    client_id: &str,
+) -> Result<DeviceCode, BoxError> {
+    log_debug!("twitch-auth", "Requesting device code");
+    let scopes = login_scopes().join(" ");
+    let response = http_client
+        .post(endpoints().auth("device"))
+        .form(&[("client_id", client_id), ("scopes", scopes.as_str())])
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let error_text = response.text().await?;
+        return Err(format!("Failed to start login: {}", error_text).into());
+    }
+
+    Ok(response.json().await?)
+}
+
+pub async fn poll_device_token(
+    http_client: &reqwest::Client,
+    client_id: &str,
+    device_code: &DeviceCode,
+) -> Result<TokenPair, BoxError> {
+    let scopes = login_scopes().join(" ");
+    let deadline = Instant::now() + Duration::from_secs(device_code.expires_in);
+    let mut interval = Duration::from_secs(device_code.interval.max(1));
+
+    while Instant::now() < deadline {
+        time::sleep(interval).await;
+
+        let response = http_client
+            .post(endpoints().auth("token"))
+            .form(&[
+                ("client_id", client_id),
+                ("scopes", scopes.as_str()),
+                ("device_code", device_code.device_code.as_str()),
+                ("grant_type", DEVICE_CODE_GRANT_TYPE),
+            ])
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            log_debug!("twitch-auth", "Device code authorized");
+            return Ok(response.json().await?);
+        }
+
+        let error: OAuthErrorResponse = response.json().await?;
+        match error.message.as_str() {
+            "authorization_pending" => {
+                log_debug!("twitch-auth", "Waiting for user to authorize device code");
+            }
+            "slow_down" => {
+                interval += Duration::from_secs(5);
+            }
+            other => return Err(format!("Login failed: {}", other).into()),
+        }
+    }
+
+    Err("Login code expired, please try again".into())
+}
+
+/// Twitch rotates refresh tokens, so the stored one stops working after this.
+pub async fn refresh_stored_token(
+    http_client: &reqwest::Client,
+    client_id: &str,
+) -> Result<String, BoxError> {
+    let refresh_token = match CredentialStore::load_refresh_token() {
+        Ok(token) => token,
+        Err(CredentialError::NotFound) => {
+            return Err(TwitchError::Unauthorized(
+                "Token expired and no refresh token is available, please log in again".to_string(),
+            )
+            .into());
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    log_debug!("twitch-auth", "Refreshing access token");
+    let response = http_client
+        .post(endpoints().auth("token"))
+        .form(&[
+            ("client_id", client_id),
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token.as_str()),
+        ])
+        .send()
+        .await?;
+
+    if response.status() == reqwest::StatusCode::BAD_REQUEST
+        || response.status() == reqwest::StatusCode::UNAUTHORIZED
+    {
+        let error_text = response.text().await?;
+        log_debug!("twitch-auth", "Refresh failed: {}", error_text);
+        return Err(TwitchError::Unauthorized(
+            "Refresh token was rejected, please log in again".to_string(),
+        )
+        .into());
+    }
+
+    let tokens: TokenPair = response.error_for_status()?.json().await?;
+    CredentialStore::save_token(&tokens.access_token)?;
+    CredentialStore::save_refresh_token(&tokens.refresh_token)?;
+
+    log_info!("twitch-auth", "Access token refreshed");
+    Ok(tokens.access_token)
+}
+
+pub async fn run_device_login(mut events: mpsc::Sender<TwitchEvent>) {
+    let http_client = reqwest::Client::new();
+
+    let result = async {
+        let client_id = endpoints().client_id.as_str();
+        let device_code = request_device_code(&http_client, client_id).await?;
+        log_info!(
+            "twitch-auth",
+            "Enter code {} at {} to log in",
+            device_code.user_code,
+            device_code.verification_uri
+        );
+        let _ = events
+            .send(TwitchEvent::LoginPending {
+                user_code: device_code.user_code.clone(),
+                verification_uri: device_code.verification_uri.clone(),
+            })
+            .await;
+
+        let tokens = poll_device_token(&http_client, client_id, &device_code).await?;
+        CredentialStore::save_token(&tokens.access_token)?;
+        CredentialStore::save_refresh_token(&tokens.refresh_token)?;
+        Ok::<(), BoxError>(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => {
+            log_info!("twitch-auth", "Logged in with Twitch");
+            let _ = events.send(TwitchEvent::LoggedIn).await;
+        }
+        Err(e) => {
+            log_error!("twitch-auth", "Login failed: {}", e);
+            let _ = events.send(TwitchEvent::LoginFailed(e.to_string())).await;
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tokio::time::{Duration, Instant};
+
+pub const DEFAULT_GLOBAL_COOLDOWN_SECONDS: u64 = 1;
+pub const DEFAULT_USER_COOLDOWN_SECONDS: u64 = 0;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CooldownSettings {
+    pub global_seconds: u64,
+    pub user_seconds: u64,
+    pub privileged_bypass: bool,
+    pub reply_on_cooldown: bool,
+}
+
+impl Default for CooldownSettings {
+    fn default() -> Self {
+        Self {
+            global_seconds: DEFAULT_GLOBAL_COOLDOWN_SECONDS,
+            user_seconds: DEFAULT_USER_COOLDOWN_SECONDS,
+            privileged_bypass: true,
+            reply_on_cooldown: false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CooldownTracker {
+    global: HashMap<String, Instant>,
+    users: HashMap<(String, String), Instant>,
+    notices: HashMap<(String, String), Instant>,
+}
+
+impl CooldownTracker {
+    pub fn remaining(
+        &self,
+        settings: &CooldownSettings,
+        trigger: &str,
+        user_id: &str,
+        now: Instant,
+    ) -> Option<Duration> {
+        let left = |last: Option<&Instant>, seconds: u64| {
+            last.map(|last| Duration::from_secs(seconds).saturating_sub(now - *last))
+                .unwrap_or_default()
+        };
+
+        let global = left(self.global.get(trigger), settings.global_seconds);
+        let user = left(
+            self.users.get(&(trigger.to_string(), user_id.to_string())),
+            settings.user_seconds,
+        );
+
+        let remaining = global.max(user);
+        (!remaining.is_zero()).then_some(remaining)
+    }
+
+    pub fn should_notify(
+        &mut self,
+        trigger: &str,
+        user_id: &str,
+        remaining: Duration,
+        now: Instant,
+    ) -> bool {
+        self.notices.retain(|_, until| *until > now);
+
+        let key = (trigger.to_string(), user_id.to_string());
+        if self.notices.contains_key(&key) {
+            return false;
+        }
+        self.notices.insert(key, now + remaining);
+        true
+    }
+
+    pub fn record(
+        &mut self,
+        settings: &CooldownSettings,
+        trigger: &str,
+        user_id: &str,
+        now: Instant,
+    ) {
+        // expired per-user entries would otherwise pile up over a long stream
+        let user_cooldown = Duration::from_secs(settings.user_seconds);
+        self.users.retain(|_, last| now - *last < user_cooldown);
+
+        self.global.insert(trigger.to_string(), now);
+        self.users
+            .insert((trigger.to_string(), user_id.to_string()), now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(global_seconds: u64, user_seconds: u64) -> CooldownSettings {
+        CooldownSettings {
+            global_seconds,
+            user_seconds,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn applies_the_longer_of_both_cooldowns() {
+        let settings = settings(5, 30);
+        let now = Instant::now();
+        let mut tracker = CooldownTracker::default();
+        tracker.record(&settings, "!np", "a", now);
+
+        let later = now + Duration::from_secs(10);
+        assert_eq!(
+            tracker.remaining(&settings, "!np", "a", later),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(tracker.remaining(&settings, "!np", "b", later), None);
+        assert_eq!(tracker.remaining(&settings, "!pp", "a", now), None);
+    }
+
+    #[test]
+    fn notifies_once_per_cooldown() {
+        let now = Instant::now();
+        let remaining = Duration::from_secs(5);
+        let mut tracker = CooldownTracker::default();
+
+        assert!(tracker.should_notify("!np", "a", remaining, now));
+        assert!(!tracker.should_notify("!np", "a", remaining, now + Duration::from_secs(1)));
+        assert!(tracker.should_notify("!np", "b", remaining, now));
+        assert!(tracker.should_notify("!np", "a", remaining, now + remaining));
+    }
+}
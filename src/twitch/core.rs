@@ -0,0 +1,1876 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use iced::futures::channel::mpsc;
+use iced::futures::stream::{SplitSink, SplitStream};
+use iced::futures::{FutureExt, SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::time::{self, Duration, Instant};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async};
+
+use super::announce::{AnnounceSettings, Announcer};
+use super::auth::{
+    REDEMPTIONS_SCOPE, TokenInfo, VALIDATION_INTERVAL_SECONDS, delivery_scope,
+    refresh_stored_token, validate_token,
+};
+use super::cooldown::CooldownSettings;
+use super::dispatch::{
+    BotReply, ChatDispatcher, Dispatched, PendingRequest, TestMessage, render_template,
+};
+use super::endpoints::endpoints;
+use super::message::{LongMessageMode, MAX_MESSAGE_CHARS, split_message};
+use super::ratelimit::RateLimit;
+use super::shared_chat::SharedChatSettings;
+use crate::commands::{AnnouncementColor, ChatRole, CustomCommand, DeliveryMode};
+use crate::osu::core::{BeatmapData, MemoryEvent, OsuCommand};
+use crate::requests::{self, Enqueued, RequestSource, find_beatmap_link, parse_beatmap_input};
+use crate::responses::{ResponseField, Responses};
+use crate::{log_debug, log_error, log_info, log_warn};
+
+const SOCKET_KEEPALIVE_SECONDS: u64 = 30;
+const RECONNECT_BASE_DELAY_MS: u64 = 1000;
+const RECONNECT_MAX_DELAY_MS: u64 = 60_000;
+// sessions that stay up at least this long reset the backoff
+const STABLE_SESSION_SECONDS: u64 = 60;
+const SEND_MAX_ATTEMPTS: u32 = 4;
+// messages beyond this many waiting ones are dropped, e.g. while Twitch is rate limiting
+const OUTGOING_QUEUE_CAPACITY: usize = 50;
+const REMEMBERED_SENT_MESSAGES: usize = 20;
+// notifications can arrive twice around a session migration
+const REMEMBERED_NOTIFICATIONS: usize = 100;
+// commands the osu worker hasn't answered by then get a reply saying so, sooner in tests
+const PENDING_REQUEST_TIMEOUT_SECONDS: u64 = if cfg!(test) { 1 } else { 10 };
+
+const REQUEST_REWARD_TITLE: &str = "Request a beatmap";
+const REQUEST_REWARD_PROMPT: &str = "Paste an osu! beatmap link";
+const REQUEST_REWARD_COST: u32 = 1000;
+
+pub(crate) const REDEMPTION_SUBSCRIPTION: &str =
+    "channel.channel_points_custom_reward_redemption.add";
+
+// correlates beatmap requests with the osu worker's responses
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Error)]
+pub enum TwitchError {
+    #[error("Authentication failed: {0}")]
+    Unauthorized(String),
+}
+
+fn is_auth_error(e: &BoxError) -> bool {
+    matches!(
+        e.downcast_ref::<TwitchError>(),
+        Some(TwitchError::Unauthorized(_))
+    )
+}
+
+fn reconnect_delay(attempt: u32) -> Duration {
+    let exp = RECONNECT_BASE_DELAY_MS.saturating_mul(1 << attempt.saturating_sub(1).min(16));
+    let capped = exp.min(RECONNECT_MAX_DELAY_MS);
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64)
+        .unwrap_or(0);
+    let jitter = nanos % (capped / 2 + 1);
+
+    Duration::from_millis(capped - jitter)
+}
+
+#[derive(Default, Clone)]
+pub enum TwitchStatus {
+    #[default]
+    Disconnected,
+    Connecting,
+    Connected(String),
+    Reconnecting(u32),
+    Error(String),
+}
+
+impl Display for TwitchStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TwitchStatus::Disconnected => write!(f, "Disconnected"),
+            TwitchStatus::Connecting => write!(f, "Connecting..."),
+            TwitchStatus::Connected(user) => write!(f, "Connected as {}", user),
+            TwitchStatus::Reconnecting(attempt) => {
+                write!(f, "Reconnecting (attempt {})...", attempt)
+            }
+            TwitchStatus::Error(e) => write!(f, "Error: {}", e),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TwitchCommand {
+    Connect {
+        token: String,
+        settings: ChatbotSettings,
+        channel_login: String,
+    },
+    Disconnect,
+    Login,
+    CancelLogin,
+    UpdatePreferences(PreferencesUpdate),
+    ResolveRedemption {
+        reward_id: String,
+        redemption_id: String,
+        fulfilled: bool,
+    },
+    CreateRequestReward,
+    ConfirmRequest {
+        message_id: String,
+    },
+    TestChat {
+        message: TestMessage,
+        settings: ChatbotSettings,
+        beatmap: Option<Box<BeatmapData>>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum TwitchEvent {
+    Connected(String),
+    TokenValidated(TokenInfo),
+    Reconnecting(u32),
+    Disconnected,
+    Error(String),
+    LoginPending {
+        user_code: String,
+        verification_uri: String,
+    },
+    LoggedIn,
+    LoginFailed(String),
+    RequestsChanged,
+    RewardsLoaded(Vec<CustomReward>),
+    RewardCreated(CustomReward),
+    PreferencesChanged(Box<PreferencesUpdate>),
+    TestChatAnswered(Option<BotReply>),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChatbotSettings {
+    pub enabled: bool,
+    pub commands: Vec<CustomCommand>,
+    pub cooldowns: CooldownSettings,
+    pub request_min_role: ChatRole,
+    pub request_reward_id: String,
+    pub announcements: AnnounceSettings,
+    pub long_messages: LongMessageMode,
+    pub ignored_users: Vec<String>,
+    pub shared_chat: SharedChatSettings,
+    pub responses: Responses,
+}
+
+/// Settings changed while connected. Settings left at `None` stay as they are.
+#[derive(Debug, Clone, Default)]
+pub struct PreferencesUpdate {
+    pub enabled: Option<bool>,
+    pub commands: Option<Vec<CustomCommand>>,
+    pub cooldowns: Option<CooldownSettings>,
+    pub request_min_role: Option<ChatRole>,
+    pub request_reward_id: Option<String>,
+    pub announcements: Option<AnnounceSettings>,
+    pub long_messages: Option<LongMessageMode>,
+    pub ignored_users: Option<Vec<String>>,
+    pub shared_chat: Option<SharedChatSettings>,
+    pub responses: Option<Responses>,
+}
+
+impl From<ChatbotSettings> for PreferencesUpdate {
+    fn from(settings: ChatbotSettings) -> Self {
+        Self {
+            enabled: Some(settings.enabled),
+            commands: Some(settings.commands),
+            cooldowns: Some(settings.cooldowns),
+            request_min_role: Some(settings.request_min_role),
+            request_reward_id: Some(settings.request_reward_id),
+            announcements: Some(settings.announcements),
+            long_messages: Some(settings.long_messages),
+            ignored_users: Some(settings.ignored_users),
+            shared_chat: Some(settings.shared_chat),
+            responses: Some(settings.responses),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessageEvent {
+    pub badges: Vec<Badge>,
+    pub broadcaster_user_id: String,
+    pub broadcaster_user_login: String,
+    pub broadcaster_user_name: String,
+    pub channel_points_animation_id: Option<String>,
+    pub channel_points_custom_reward_id: Option<String>,
+    pub chatter_user_id: String,
+    pub chatter_user_login: String,
+    pub chatter_user_name: String,
+    pub cheer: Option<Cheer>,
+    pub color: String,
+    pub is_source_only: Option<bool>,
+    pub message: ChatMessage,
+    pub message_id: String,
+    pub message_type: ChatMessageType,
+    pub reply: Option<Reply>,
+    pub source_badges: Option<Vec<Badge>>,
+    pub source_broadcaster_user_id: Option<String>,
+    pub source_broadcaster_user_login: Option<String>,
+    pub source_broadcaster_user_name: Option<String>,
+    pub source_message_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Badge {
+    pub set_id: String,
+    pub id: String,
+    pub info: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CustomReward {
+    pub id: String,
+    pub title: String,
+}
+
+impl Display for CustomReward {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.title)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct HelixResponse<T> {
+    data: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SentMessage {
+    #[serde(default)]
+    message_id: String,
+    is_sent: bool,
+    drop_reason: Option<DropReason>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DropReason {
+    code: String,
+    message: String,
+}
+
+#[derive(Debug)]
+struct OwnMessage {
+    text: String,
+    message_id: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct OutgoingMessage {
+    channel_id: String,
+    text: String,
+    delivery: Delivery,
+}
+
+#[derive(Debug, Clone)]
+enum Delivery {
+    Chat {
+        reply_parent_message_id: Option<String>,
+    },
+    Announcement(AnnouncementColor),
+    Whisper {
+        user_id: String,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct RedemptionEvent {
+    id: String,
+    user_login: String,
+    user_name: String,
+    #[serde(default)]
+    user_input: String,
+    status: String,
+    reward: RedemptionReward,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct RedemptionReward {
+    id: String,
+}
+
+impl ChatMessage {
+    pub fn beatmap_link(&self) -> Option<u32> {
+        if self.fragments.is_empty() {
+            return find_beatmap_link(&self.text);
+        }
+        self.fragments
+            .iter()
+            .filter(|f| matches!(f.fragment_type, FragmentType::Text))
+            .find_map(|f| find_beatmap_link(&f.text))
+    }
+}
+
+impl ChatMessageEvent {
+    pub fn role(&self) -> ChatRole {
+        if self.chatter_user_id == self.broadcaster_user_id {
+            return ChatRole::Broadcaster;
+        }
+        self.badges
+            .iter()
+            .filter_map(|b| ChatRole::from_badge(&b.set_id))
+            .max()
+            .unwrap_or_default()
+    }
+
+    pub fn shared_from(&self) -> Option<&str> {
+        match &self.source_broadcaster_user_id {
+            Some(source_id) if *source_id != self.broadcaster_user_id => {
+                self.source_broadcaster_user_login.as_deref()
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_privileged(&self) -> bool {
+        self.role() >= ChatRole::Moderator
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub text: String,
+    pub fragments: Vec<ChatMessageFragment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessageFragment {
+    #[serde(rename = "type")]
+    pub fragment_type: FragmentType,
+    pub text: String,
+    pub cheermote: Option<Cheermote>,
+    pub emote: Option<Emote>,
+    pub mention: Option<Mention>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FragmentType {
+    Text,
+    Cheermote,
+    Emote,
+    Mention,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatMessageType {
+    Text,
+    ChannelPointsHighlighted,
+    ChannelPointsSubOnly,
+    UserIntro,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cheermote {
+    pub prefix: String,
+    pub bits: u32,
+    pub tier: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Emote {
+    pub id: String,
+    pub emote_set_id: String,
+    pub owner_id: String,
+    pub format: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mention {
+    pub user_id: String,
+    pub user_name: String,
+    pub user_login: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cheer {
+    pub bits: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reply {
+    pub parent_message_id: String,
+    pub parent_message_body: String,
+    pub parent_user_id: String,
+    pub parent_user_name: String,
+    pub parent_user_login: String,
+    pub thread_message_id: String,
+    pub thread_user_id: String,
+    pub thread_user_name: String,
+    pub thread_user_login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TwitchResponse {
+    data: Vec<TwitchUser>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[allow(dead_code)]
+pub struct TwitchUser {
+    pub id: String,
+    login: String,
+    pub display_name: String,
+    #[serde(rename = "type")]
+    user_type: String,
+    broadcaster_type: String,
+    description: String,
+    profile_image_url: String,
+    offline_image_url: String,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SubscriptionRequest {
+    #[serde(rename = "type")]
+    sub_type: String,
+    version: String,
+    condition: serde_json::Value,
+    transport: Transport,
+}
+
+#[derive(Debug, Serialize)]
+struct Transport {
+    method: String,
+    session_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct EventMessage {
+    metadata: EventMetadata,
+    payload: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventMetadata {
+    message_id: String,
+    message_type: String,
+    #[allow(dead_code)]
+    message_timestamp: String,
+    #[serde(default)]
+    subscription_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WelcomePayload {
+    session: SessionData,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[allow(dead_code)]
+struct SessionData {
+    id: String,
+    keepalive_timeout_seconds: Option<u64>,
+    #[serde(default)]
+    reconnect_url: Option<String>,
+}
+
+type WebSocketType = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+struct Session {
+    data: SessionData,
+    write: Arc<Mutex<SplitSink<WebSocketType, Message>>>,
+    read: Arc<Mutex<SplitStream<WebSocketType>>>,
+}
+
+#[derive(Clone)]
+pub struct ChatbotPreferences {
+    pub enabled: Arc<Mutex<bool>>,
+    pub commands: Arc<Mutex<Vec<CustomCommand>>>,
+    pub cooldowns: Arc<Mutex<CooldownSettings>>,
+    pub request_min_role: Arc<Mutex<ChatRole>>,
+    pub request_reward_id: Arc<Mutex<String>>,
+    pub announcements: Arc<Mutex<AnnounceSettings>>,
+    pub long_messages: Arc<Mutex<LongMessageMode>>,
+    pub ignored_users: Arc<Mutex<Vec<String>>>,
+    pub shared_chat: Arc<Mutex<SharedChatSettings>>,
+    pub responses: Arc<Mutex<Responses>>,
+}
+
+impl ChatbotPreferences {
+    pub fn new(settings: ChatbotSettings) -> Self {
+        Self {
+            enabled: Arc::new(Mutex::new(settings.enabled)),
+            commands: Arc::new(Mutex::new(settings.commands)),
+            cooldowns: Arc::new(Mutex::new(settings.cooldowns)),
+            request_min_role: Arc::new(Mutex::new(settings.request_min_role)),
+            request_reward_id: Arc::new(Mutex::new(settings.request_reward_id)),
+            announcements: Arc::new(Mutex::new(settings.announcements)),
+            long_messages: Arc::new(Mutex::new(settings.long_messages)),
+            ignored_users: Arc::new(Mutex::new(settings.ignored_users)),
+            shared_chat: Arc::new(Mutex::new(settings.shared_chat)),
+            responses: Arc::new(Mutex::new(settings.responses)),
+        }
+    }
+
+    pub async fn update(&self, update: PreferencesUpdate) {
+        if let Some(enabled) = update.enabled {
+            log_debug!("twitch", "Updated enabled to: {}", enabled);
+            *self.enabled.lock().await = enabled;
+        }
+        if let Some(commands) = update.commands {
+            log_debug!("twitch", "Updated commands ({} configured)", commands.len());
+            *self.commands.lock().await = commands;
+        }
+        if let Some(cooldowns) = update.cooldowns {
+            log_debug!("twitch", "Updated cooldowns to: {:?}", cooldowns);
+            *self.cooldowns.lock().await = cooldowns;
+        }
+        if let Some(role) = update.request_min_role {
+            log_debug!("twitch", "Updated request role to: {}", role);
+            *self.request_min_role.lock().await = role;
+        }
+        if let Some(reward_id) = update.request_reward_id {
+            log_debug!("twitch", "Updated request reward to: {}", reward_id);
+            *self.request_reward_id.lock().await = reward_id;
+        }
+        if let Some(announcements) = update.announcements {
+            log_debug!("twitch", "Updated announcements to: {:?}", announcements);
+            *self.announcements.lock().await = announcements;
+        }
+        if let Some(mode) = update.long_messages {
+            log_debug!("twitch", "Updated long message handling to: {:?}", mode);
+            *self.long_messages.lock().await = mode;
+        }
+        if let Some(ignored_users) = update.ignored_users {
+            log_debug!("twitch", "Updated ignored users to: {:?}", ignored_users);
+            *self.ignored_users.lock().await = ignored_users;
+        }
+        if let Some(shared_chat) = update.shared_chat {
+            log_debug!("twitch", "Updated shared chat to: {:?}", shared_chat);
+            *self.shared_chat.lock().await = shared_chat;
+        }
+        if let Some(responses) = update.responses {
+            log_debug!("twitch", "Updated bot responses");
+            *self.responses.lock().await = responses;
+        }
+    }
+}
+
+pub struct TwitchClient {
+    client_id: String,
+    pub user: TwitchUser,
+    /// Same as `user` unless a separate bot account is used.
+    pub channel: TwitchUser,
+    pub token_info: TokenInfo,
+    session: Mutex<Session>,
+    // also serializes refreshes, so concurrent 401s only trigger one of them
+    access_token: Mutex<String>,
+    http_client: reqwest::Client,
+    outgoing_tx: tokio::sync::mpsc::Sender<OutgoingMessage>,
+    outgoing_rx: Mutex<tokio::sync::mpsc::Receiver<OutgoingMessage>>,
+    own_messages: std::sync::Mutex<VecDeque<OwnMessage>>,
+    notification_ids: std::sync::Mutex<VecDeque<String>>,
+    pub chatbot_preferences: ChatbotPreferences,
+    dispatcher: Mutex<ChatDispatcher>,
+    pending_requests: Mutex<HashMap<u64, (Instant, PendingRequest)>>,
+    announcer: std::sync::Mutex<Announcer>,
+}
+
+impl TwitchClient {
+    pub async fn new(
+        access_token: &str,
+        settings: ChatbotSettings,
+        channel_login: &str,
+    ) -> Result<Self, BoxError> {
+        log_debug!("twitch", "Creating new TwitchClient");
+        let client_id = endpoints().client_id.as_str();
+
+        let http_client = reqwest::Client::new();
+
+        let (access_token, token_info) =
+            match validate_token(&http_client, client_id, access_token).await {
+                Ok(info) => (access_token.to_string(), info),
+                Err(e) if is_auth_error(&e) => {
+                    log_info!("twitch", "Stored token was rejected, trying to refresh it");
+                    let refreshed = refresh_stored_token(&http_client, client_id).await?;
+                    let info = validate_token(&http_client, client_id, &refreshed).await?;
+                    (refreshed, info)
+                }
+                Err(e) => return Err(e),
+            };
+
+        log_debug!("twitch", "Getting user ID from access token");
+        let user = get_user(&http_client, client_id, &access_token, None).await?;
+        log_debug!("twitch", "Got user: {}", user.display_name);
+
+        let channel_login = channel_login.trim().trim_start_matches('#');
+        let channel = if channel_login.is_empty() || channel_login.eq_ignore_ascii_case(&user.login)
+        {
+            user.clone()
+        } else {
+            get_user(&http_client, client_id, &access_token, Some(channel_login)).await?
+        };
+        log_info!(
+            "twitch",
+            "Chatting as {} in {}'s channel",
+            user.display_name,
+            channel.display_name
+        );
+
+        log_debug!("twitch", "Initializing websocket session");
+        let session = init_websocket_session(&endpoints().eventsub_url).await?;
+        let (outgoing_tx, outgoing_rx) = tokio::sync::mpsc::channel(OUTGOING_QUEUE_CAPACITY);
+        let chatbot_preferences = ChatbotPreferences::new(settings);
+
+        Ok(Self {
+            client_id: client_id.to_string(),
+            user,
+            channel,
+            token_info,
+            session: Mutex::new(session),
+            access_token: Mutex::new(access_token),
+            http_client,
+            outgoing_tx,
+            outgoing_rx: Mutex::new(outgoing_rx),
+            own_messages: std::sync::Mutex::new(VecDeque::new()),
+            notification_ids: std::sync::Mutex::new(VecDeque::new()),
+            dispatcher: Mutex::new(ChatDispatcher::new(chatbot_preferences.clone())),
+            pending_requests: Mutex::new(HashMap::new()),
+            announcer: std::sync::Mutex::new(Announcer::default()),
+            chatbot_preferences,
+        })
+    }
+
+    pub async fn update_preferences(&self, update: PreferencesUpdate) {
+        self.chatbot_preferences.update(update).await;
+    }
+
+    pub async fn subscribe_to_channel_messages(&self, channel_id: &str) -> Result<(), BoxError> {
+        log_debug!(
+            "twitch",
+            "Initializing chat message eventsub for user {} in channel {}",
+            self.user.id,
+            channel_id
+        );
+        self.subscribe(
+            "channel.chat.message",
+            serde_json::json!({
+                "broadcaster_user_id": channel_id,
+                "user_id": self.user.id
+            }),
+        )
+        .await?;
+
+        log_debug!("twitch", "Successfully initialized chat message eventsub");
+        Ok(())
+    }
+
+    pub async fn subscribe_to_redemptions(&self) {
+        if !self.is_broadcaster() {
+            log_info!(
+                "twitch",
+                "Channel Points requests need the broadcaster's own login, skipping them"
+            );
+            return;
+        }
+
+        if !self.token_info.has_scope(REDEMPTIONS_SCOPE) {
+            log_info!(
+                "twitch",
+                "Token lacks {}, log in again to take requests through Channel Points",
+                REDEMPTIONS_SCOPE
+            );
+            return;
+        }
+
+        if let Err(e) = self
+            .subscribe(
+                REDEMPTION_SUBSCRIPTION,
+                serde_json::json!({ "broadcaster_user_id": self.user.id }),
+            )
+            .await
+        {
+            log_warn!("twitch", "Failed to subscribe to redemptions: {}", e);
+        }
+    }
+
+    pub fn is_broadcaster(&self) -> bool {
+        self.user.id == self.channel.id
+    }
+
+    async fn subscribe(
+        &self,
+        sub_type: &str,
+        condition: serde_json::Value,
+    ) -> Result<(), BoxError> {
+        let request = SubscriptionRequest {
+            sub_type: sub_type.to_string(),
+            version: "1".to_string(),
+            condition,
+            transport: Transport {
+                method: "websocket".to_string(),
+                session_id: self.session.lock().await.data.id.clone(),
+            },
+        };
+
+        log_debug!(
+            "twitch",
+            "Sending eventsub subscription request for {}",
+            sub_type
+        );
+        let response = self
+            .send_helix(|| {
+                self.http_client
+                    .post(endpoints().helix("eventsub/subscriptions"))
+                    .json(&request)
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            log_debug!("twitch", "Failed to subscribe: {}", error_text);
+            if status == reqwest::StatusCode::UNAUTHORIZED
+                || status == reqwest::StatusCode::FORBIDDEN
+            {
+                if !self.is_broadcaster() {
+                    return Err(TwitchError::Unauthorized(format!(
+                        "{} can't join {}'s chat: {}",
+                        self.user.display_name, self.channel.display_name, error_text
+                    ))
+                    .into());
+                }
+                return Err(TwitchError::Unauthorized(error_text).into());
+            }
+            return Err(format!(
+                "Failed to subscribe to {} for user {}: {}",
+                sub_type, self.user.id, error_text
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_custom_rewards(&self) -> Result<Vec<CustomReward>, BoxError> {
+        let response = self
+            .send_helix(|| {
+                self.http_client
+                    .get(endpoints().helix("channel_points/custom_rewards"))
+                    .query(&[
+                        ("broadcaster_id", self.user.id.as_str()),
+                        ("only_manageable_rewards", "true"),
+                    ])
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Failed to get custom rewards: {}", error_text).into());
+        }
+
+        let rewards: HelixResponse<CustomReward> = response.json().await?;
+        Ok(rewards.data)
+    }
+
+    pub async fn create_request_reward(&self) -> Result<CustomReward, BoxError> {
+        let body = serde_json::json!({
+            "title": REQUEST_REWARD_TITLE,
+            "prompt": REQUEST_REWARD_PROMPT,
+            "cost": REQUEST_REWARD_COST,
+            "is_user_input_required": true,
+        });
+        let response = self
+            .send_helix(|| {
+                self.http_client
+                    .post(endpoints().helix("channel_points/custom_rewards"))
+                    .query(&[("broadcaster_id", self.user.id.as_str())])
+                    .json(&body)
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Failed to create reward: {}", error_text).into());
+        }
+
+        let rewards: HelixResponse<CustomReward> = response.json().await?;
+        rewards
+            .data
+            .into_iter()
+            .next()
+            .ok_or_else(|| "Twitch didn't return the created reward".into())
+    }
+
+    /// Only works for rewards created with the same client id.
+    pub async fn update_redemption_status(
+        &self,
+        reward_id: &str,
+        redemption_id: &str,
+        fulfilled: bool,
+    ) -> Result<(), BoxError> {
+        let status = if fulfilled { "FULFILLED" } else { "CANCELED" };
+        let response = self
+            .send_helix(|| {
+                self.http_client
+                    .patch(endpoints().helix("channel_points/custom_rewards/redemptions"))
+                    .query(&[
+                        ("broadcaster_id", self.user.id.as_str()),
+                        ("reward_id", reward_id),
+                        ("id", redemption_id),
+                    ])
+                    .json(&serde_json::json!({ "status": status }))
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Failed to update redemption: {}", error_text).into());
+        }
+
+        log_debug!(
+            "twitch",
+            "Marked redemption {} as {}",
+            redemption_id,
+            status
+        );
+        Ok(())
+    }
+
+    /// Runs until an authentication error makes further attempts pointless.
+    pub async fn run_supervised(
+        &self,
+        osu_tx: mpsc::Sender<OsuCommand>,
+        osu_rx: mpsc::Receiver<MemoryEvent>,
+        mut events: mpsc::Sender<TwitchEvent>,
+    ) {
+        tokio::select! {
+            _ = self.supervise(osu_tx, osu_rx, events.clone()) => {}
+            _ = self.dispatch_messages() => {}
+            _ = self.expire_pending_requests() => {}
+            e = self.monitor_token(events.clone()) => {
+                log_error!("twitch", "Token validation failed: {}", e);
+                let _ = events.send(TwitchEvent::Error(e.to_string())).await;
+            }
+        }
+    }
+
+    async fn supervise(
+        &self,
+        osu_tx: mpsc::Sender<OsuCommand>,
+        mut osu_rx: mpsc::Receiver<MemoryEvent>,
+        mut events: mpsc::Sender<TwitchEvent>,
+    ) {
+        let mut attempt: u32 = 0;
+
+        loop {
+            let session_start = Instant::now();
+            let result = self
+                .init_websocket_handler(osu_tx.clone(), &mut osu_rx, events.clone())
+                .await;
+
+            match result {
+                Err(e) if is_auth_error(&e) => {
+                    log_error!("twitch", "Websocket handler error: {}", e);
+                    let _ = events.send(TwitchEvent::Error(e.to_string())).await;
+                    return;
+                }
+                Err(e) => {
+                    log_warn!("twitch", "Connection lost: {}", e);
+                }
+                Ok(()) => {
+                    log_warn!("twitch", "Connection closed by server");
+                }
+            }
+
+            if session_start.elapsed() >= Duration::from_secs(STABLE_SESSION_SECONDS) {
+                attempt = 0;
+            }
+
+            loop {
+                attempt += 1;
+                let delay = reconnect_delay(attempt);
+                log_info!(
+                    "twitch",
+                    "Reconnecting in {:.1}s (attempt {})",
+                    delay.as_secs_f32(),
+                    attempt
+                );
+                let _ = events.send(TwitchEvent::Reconnecting(attempt)).await;
+                time::sleep(delay).await;
+
+                match self.rebuild_session().await {
+                    Ok(()) => break,
+                    Err(e) if is_auth_error(&e) => {
+                        log_error!("twitch", "Reconnect failed: {}", e);
+                        let _ = events.send(TwitchEvent::Error(e.to_string())).await;
+                        return;
+                    }
+                    Err(e) => {
+                        log_warn!("twitch", "Reconnect attempt {} failed: {}", attempt, e);
+                    }
+                }
+            }
+
+            log_info!("twitch", "Reconnected after {} attempt(s)", attempt);
+            let _ = events
+                .send(TwitchEvent::Connected(self.user.display_name.clone()))
+                .await;
+        }
+    }
+
+    async fn expire_pending_requests(&self) {
+        let timeout = Duration::from_secs(PENDING_REQUEST_TIMEOUT_SECONDS);
+        let mut interval = time::interval(Duration::from_secs(1));
+
+        loop {
+            interval.tick().await;
+            let now = Instant::now();
+            let expired: Vec<PendingRequest> = {
+                let mut pending_requests = self.pending_requests.lock().await;
+                let expired_ids: Vec<u64> = pending_requests
+                    .iter()
+                    .filter(|(_, (requested_at, _))| now - *requested_at >= timeout)
+                    .map(|(request_id, _)| *request_id)
+                    .collect();
+                expired_ids
+                    .into_iter()
+                    .filter_map(|request_id| pending_requests.remove(&request_id))
+                    .map(|(_, request)| request)
+                    .collect()
+            };
+
+            for request in expired {
+                log_warn!("twitch", "osu! didn't provide beatmap data in time");
+                let reply = self.dispatcher.lock().await.time_out(request).await;
+                self.send_reply(reply);
+            }
+        }
+    }
+
+    async fn monitor_token(&self, mut events: mpsc::Sender<TwitchEvent>) -> BoxError {
+        let mut interval = time::interval(Duration::from_secs(VALIDATION_INTERVAL_SECONDS));
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            let token = self.access_token.lock().await.clone();
+            let result = match validate_token(&self.http_client, &self.client_id, &token).await {
+                Err(e) if is_auth_error(&e) => match self.refresh_access_token(&token).await {
+                    Ok(()) => {
+                        let token = self.access_token.lock().await.clone();
+                        validate_token(&self.http_client, &self.client_id, &token).await
+                    }
+                    Err(e) => Err(e),
+                },
+                result => result,
+            };
+
+            match result {
+                Ok(info) => {
+                    let _ = events.send(TwitchEvent::TokenValidated(info)).await;
+                }
+                Err(e) if is_auth_error(&e) => return e,
+                Err(e) => {
+                    log_warn!("twitch", "Token validation failed: {}", e);
+                }
+            }
+        }
+    }
+
+    async fn send_helix<F>(&self, build: F) -> Result<reqwest::Response, BoxError>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        let token = self.access_token.lock().await.clone();
+        let response = build()
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Client-Id", &self.client_id)
+            .send()
+            .await?;
+
+        if response.status() != reqwest::StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        log_info!("twitch", "Access token was rejected, refreshing");
+        self.refresh_access_token(&token).await?;
+
+        let token = self.access_token.lock().await.clone();
+        Ok(build()
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Client-Id", &self.client_id)
+            .send()
+            .await?)
+    }
+
+    async fn refresh_access_token(&self, stale_token: &str) -> Result<(), BoxError> {
+        let mut token = self.access_token.lock().await;
+        if *token != stale_token {
+            return Ok(());
+        }
+
+        *token = refresh_stored_token(&self.http_client, &self.client_id).await?;
+        Ok(())
+    }
+
+    async fn rebuild_session(&self) -> Result<(), BoxError> {
+        let new_session = init_websocket_session(&endpoints().eventsub_url).await?;
+        let old_session = std::mem::replace(&mut *self.session.lock().await, new_session);
+        let _ = old_session.write.lock().await.close().await;
+
+        self.subscribe_to_channel_messages(&self.channel.id).await?;
+        self.subscribe_to_redemptions().await;
+        Ok(())
+    }
+
+    pub async fn init_websocket_handler(
+        &self,
+        osu_tx: mpsc::Sender<OsuCommand>,
+        osu_rx: &mut mpsc::Receiver<MemoryEvent>,
+        events: mpsc::Sender<TwitchEvent>,
+    ) -> Result<(), BoxError> {
+        log_debug!("twitch", "Starting websocket session handler");
+        let keepalive_duration = Duration::from_secs(SOCKET_KEEPALIVE_SECONDS);
+        let mut last_message = Instant::now();
+
+        loop {
+            let announce_settings = self.chatbot_preferences.announcements.lock().await.clone();
+            let announce_at = self.announcer.lock().unwrap().deadline(&announce_settings);
+
+            let read_half = Arc::clone(&self.session.lock().await.read);
+            let mut read = read_half.lock().await;
+            let timeout = time::timeout(keepalive_duration, read.next());
+
+            tokio::select! {
+                result = timeout => {
+                    match result {
+                        Ok(Some(Ok(msg))) => {
+                            log_debug!("twitch", "Received message from server");
+                            last_message = Instant::now();
+
+                            match msg {
+                                Message::Text(text) => {
+                                    log_debug!("twitch", "Processing text message");
+                                    drop(read);
+                                    let reconnect_url = match self.handle_eventsub_message(
+                                        &text,
+                                        osu_tx.clone(),
+                                        events.clone(),
+                                    ).await {
+                                        Ok(reconnect_url) => reconnect_url,
+                                        Err(e) if is_auth_error(&e) => return Err(e),
+                                        Err(e) => {
+                                            log_warn!("twitch", "Message error: {}", e);
+                                            None
+                                        }
+                                    };
+
+                                    if let Some(url) = reconnect_url {
+                                        // messages that arrived on the old socket before the
+                                        // migration still need to be handled
+                                        for text in self.migrate_session(&url).await? {
+                                            if let Err(e) = self.handle_eventsub_message(
+                                                &text,
+                                                osu_tx.clone(),
+                                                events.clone(),
+                                            ).await {
+                                                log_warn!("twitch", "Message error: {}", e);
+                                            }
+                                        }
+                                        last_message = Instant::now();
+                                    }
+                                }
+                                Message::Ping(data) => {
+                                    log_debug!("twitch", "Received ping, sending pong");
+                                    drop(read);
+                                    let write_half = Arc::clone(&self.session.lock().await.write);
+                                    write_half.lock().await.send(Message::Pong(data)).await?;
+                                }
+                                Message::Close(_) => {
+                                    log_info!("twitch", "Connection closed by server");
+                                    drop(read);
+                                    return Ok(());
+                                }
+                                _ => {
+                                    log_debug!("twitch", "Received other message type");
+                                    drop(read);
+                                }
+                            }
+                        }
+                        Ok(Some(Err(e))) => {
+                            log_debug!("twitch", "WebSocket error: {}", e);
+                            drop(read);
+                            return Err(format!("WebSocket error: {}", e).into());
+                        }
+                        Ok(None) => {
+                            log_debug!("twitch", "WebSocket connection closed");
+                            drop(read);
+                            return Err("WebSocket connection closed".into());
+                        }
+                        Err(_) => {
+                            log_debug!("twitch", "Timeout waiting for message");
+                            if last_message.elapsed() > keepalive_duration {
+                                log_debug!("twitch", "Keepalive timeout exceeded");
+                                drop(read);
+                                return Err("Keepalive timeout".into());
+                            }
+                            drop(read);
+                        }
+                    }
+                }
+
+                Some(osu_event) = osu_rx.next() => {
+                    drop(read);
+
+                    match osu_event {
+                        MemoryEvent::BeatmapDataResponse(request_id, beatmap_data) => {
+                            let pending = self.pending_requests.lock().await.remove(&request_id);
+                            match pending {
+                                Some((_, request)) => {
+                                    let reply = self.dispatcher.lock().await.answer(request, beatmap_data).await;
+                                    self.send_reply(reply);
+                                }
+                                None => {
+                                    log_debug!("twitch", "Ignoring response to unknown request {}", request_id);
+                                }
+                            }
+                        }
+                        MemoryEvent::BeatmapChanged(beatmap) => {
+                            self.announcer.lock().unwrap().beatmap_changed(&announce_settings, beatmap.as_ref(), Instant::now());
+                        }
+                        _ => {}
+                    }
+                }
+
+                // the future is built even while the branch is disabled, hence the fallback
+                _ = time::sleep_until(announce_at.unwrap_or_else(Instant::now)), if announce_at.is_some() => {
+                    drop(read);
+
+                    let due = self.announcer.lock().unwrap().take_due(&announce_settings, Instant::now());
+                    if let Some(beatmap) = due {
+                        self.announce(&announce_settings, &beatmap).await;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn announce(&self, settings: &AnnounceSettings, beatmap: &BeatmapData) {
+        if !*self.chatbot_preferences.enabled.lock().await {
+            return;
+        }
+
+        log_info!(
+            "twitch",
+            "Announcing {} - {} [{}]",
+            beatmap.artist,
+            beatmap.title,
+            beatmap.difficulty_name
+        );
+        let mode = *self.chatbot_preferences.long_messages.lock().await;
+        let responses = self.chatbot_preferences.responses.lock().await.clone();
+        let message = render_template(&settings.template, beatmap, mode, &responses);
+        self.send_chat_message(&self.channel.id, &message, None);
+    }
+
+    fn send_reply(&self, reply: BotReply) {
+        let mut mode = reply.delivery;
+        if let Some(scope) = delivery_scope(mode)
+            && !self.token_info.has_scope(scope)
+        {
+            log_warn!(
+                "twitch",
+                "Token lacks {}, log in again to send {}s",
+                scope,
+                mode.to_string().to_lowercase()
+            );
+            mode = DeliveryMode::Reply;
+        }
+
+        let delivery = match mode {
+            DeliveryMode::Reply => Delivery::Chat {
+                reply_parent_message_id: Some(reply.parent_message_id),
+            },
+            DeliveryMode::Message => Delivery::Chat {
+                reply_parent_message_id: None,
+            },
+            DeliveryMode::Announcement => Delivery::Announcement(reply.announcement_color),
+            DeliveryMode::Whisper => Delivery::Whisper {
+                user_id: reply.chatter_id,
+            },
+        };
+        self.queue_message(&self.channel.id, &reply.text, delivery);
+    }
+
+    pub async fn confirm_request(&self, message_id: &str) {
+        let text = self
+            .chatbot_preferences
+            .responses
+            .lock()
+            .await
+            .render(ResponseField::RequestApproved, &[]);
+        self.send_chat_message(&self.channel.id, &text, Some(message_id));
+    }
+
+    async fn migrate_session(&self, reconnect_url: &str) -> Result<Vec<String>, BoxError> {
+        log_info!("twitch", "Server requested reconnect, migrating session");
+        let new_session = init_websocket_session(reconnect_url).await?;
+        let old_session = std::mem::replace(&mut *self.session.lock().await, new_session);
+
+        let mut leftovers = Vec::new();
+        let mut old_read = old_session.read.lock().await;
+        while let Some(Some(Ok(msg))) = old_read.next().now_or_never() {
+            if let Message::Text(text) = msg {
+                leftovers.push(text);
+            }
+        }
+        drop(old_read);
+
+        if let Err(e) = old_session.write.lock().await.close().await {
+            log_debug!("twitch", "Failed to close old websocket: {}", e);
+        }
+
+        log_info!("twitch", "Session migrated successfully");
+        Ok(leftovers)
+    }
+
+    async fn handle_redemption(
+        &self,
+        redemption: RedemptionEvent,
+        mut events: mpsc::Sender<TwitchEvent>,
+    ) {
+        let reward_id = self
+            .chatbot_preferences
+            .request_reward_id
+            .lock()
+            .await
+            .clone();
+        if reward_id.is_empty()
+            || redemption.reward.id != reward_id
+            || redemption.status != "unfulfilled"
+        {
+            return;
+        }
+
+        let enabled = *self.chatbot_preferences.enabled.lock().await;
+        let responses = self.chatbot_preferences.responses.lock().await.clone();
+        let beatmap_id = parse_beatmap_input(&redemption.user_input);
+        let (reply, accepted) = match beatmap_id {
+            _ if !enabled || !requests::is_accepting() => (
+                responses.render(ResponseField::RedemptionClosed, &[]),
+                false,
+            ),
+            None => (
+                responses.render(ResponseField::RedemptionInvalid, &[]),
+                false,
+            ),
+            Some(beatmap_id) => {
+                let source = RequestSource::Redemption {
+                    reward_id: redemption.reward.id.clone(),
+                    redemption_id: redemption.id.clone(),
+                };
+                match requests::enqueue(beatmap_id, &redemption.user_name, source) {
+                    Enqueued::Added(position) => {
+                        log_info!(
+                            "twitch",
+                            "{} redeemed a request for beatmap {}",
+                            redemption.user_name,
+                            beatmap_id
+                        );
+                        let _ = events.send(TwitchEvent::RequestsChanged).await;
+                        (
+                            responses.render(
+                                ResponseField::RequestAdded,
+                                &[("position", &position.to_string())],
+                            ),
+                            true,
+                        )
+                    }
+                    Enqueued::AlreadyQueued(position) => (
+                        responses.render(
+                            ResponseField::RedemptionAlreadyQueued,
+                            &[("position", &position.to_string())],
+                        ),
+                        false,
+                    ),
+                }
+            }
+        };
+
+        if !accepted
+            && let Err(e) = self
+                .update_redemption_status(&redemption.reward.id, &redemption.id, false)
+                .await
+        {
+            log_warn!("twitch", "Failed to refund redemption: {}", e);
+        }
+
+        let reply = format!("@{} {}", redemption.user_login, reply);
+        self.send_chat_message(&self.channel.id, &reply, None);
+    }
+
+    async fn handle_eventsub_message(
+        &self,
+        message: &str,
+        mut osu_tx: mpsc::Sender<OsuCommand>,
+        events: mpsc::Sender<TwitchEvent>,
+    ) -> Result<Option<String>, BoxError> {
+        let message: EventMessage = serde_json::from_str(message)?;
+
+        match message.metadata.message_type.as_str() {
+            "session_keepalive" => {
+                // expected, can ignore
+            }
+            "notification" => {
+                if !self.is_new_notification(&message.metadata.message_id) {
+                    log_debug!(
+                        "twitch",
+                        "Ignoring repeated notification {}",
+                        message.metadata.message_id
+                    );
+                    return Ok(None);
+                }
+                log_debug!(
+                    "twitch",
+                    "Received notification, subscription type: {:?}",
+                    message.metadata.subscription_type
+                );
+                if message.metadata.subscription_type.as_deref() == Some("channel.chat.message") {
+                    let event_data: Option<ChatMessageEvent> = message
+                        .payload
+                        .get("event")
+                        .and_then(|v| serde_json::from_value(v.clone()).ok());
+
+                    if let Some(event) = event_data {
+                        if self.is_own_message(&event) {
+                            log_debug!("twitch", "Ignoring own message");
+                            return Ok(None);
+                        }
+
+                        let dispatched = self
+                            .dispatcher
+                            .lock()
+                            .await
+                            .handle_message(&event, events)
+                            .await;
+                        match dispatched {
+                            Dispatched::Ignored => {}
+                            Dispatched::Reply(reply) => self.send_reply(reply),
+                            Dispatched::Command(request) => {
+                                let request_id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+                                let osu_command = OsuCommand::RequestBeatmapData(request_id);
+
+                                if let Err(e) = osu_tx.send(osu_command).await {
+                                    log_error!("twitch", "Failed to send osu command: {}", e);
+                                } else {
+                                    self.pending_requests
+                                        .lock()
+                                        .await
+                                        .insert(request_id, (Instant::now(), request));
+                                }
+                            }
+                        }
+                    }
+                } else if message.metadata.subscription_type.as_deref()
+                    == Some(REDEMPTION_SUBSCRIPTION)
+                {
+                    let redemption: RedemptionEvent = serde_json::from_value(
+                        message.payload.get("event").cloned().unwrap_or_default(),
+                    )?;
+                    self.handle_redemption(redemption, events).await;
+                }
+            }
+            "session_reconnect" => {
+                log_debug!("twitch", "Server requested reconnect");
+                let payload: WelcomePayload = serde_json::from_value(message.payload)?;
+                return match payload.session.reconnect_url {
+                    Some(url) => Ok(Some(url)),
+                    None => Err("Reconnect message is missing reconnect_url".into()),
+                };
+            }
+            "revocation" => {
+                let reason = message
+                    .payload
+                    .pointer("/subscription/status")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown")
+                    .to_string();
+                log_warn!(
+                    "twitch",
+                    "Subscription revoked: {:?} ({})",
+                    message.metadata.subscription_type,
+                    reason
+                );
+                if reason == "authorization_revoked" || reason == "user_removed" {
+                    return Err(TwitchError::Unauthorized(format!(
+                        "Subscription revoked: {}",
+                        reason
+                    ))
+                    .into());
+                }
+            }
+            _ => {
+                log_debug!(
+                    "twitch",
+                    "Unknown message type: {}",
+                    message.metadata.message_type
+                );
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn is_new_notification(&self, message_id: &str) -> bool {
+        let mut notification_ids = self.notification_ids.lock().unwrap();
+        if notification_ids.iter().any(|id| id == message_id) {
+            return false;
+        }
+        if notification_ids.len() == REMEMBERED_NOTIFICATIONS {
+            notification_ids.pop_front();
+        }
+        notification_ids.push_back(message_id.to_string());
+        true
+    }
+
+    fn is_own_message(&self, event: &ChatMessageEvent) -> bool {
+        if event.chatter_user_id != self.user.id {
+            return false;
+        }
+        if !self.is_broadcaster() {
+            return true;
+        }
+
+        let mut own_messages = self.own_messages.lock().unwrap();
+        // the echo can arrive before Twitch answers with the id, so unconfirmed messages
+        // are matched by their text
+        match own_messages.iter().position(|own| match &own.message_id {
+            Some(id) => *id == event.message_id,
+            None => own.text == event.message.text,
+        }) {
+            Some(index) => {
+                own_messages.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn expect_echo(&self, message: &OutgoingMessage) -> bool {
+        if !self.is_broadcaster() || !matches!(message.delivery, Delivery::Chat { .. }) {
+            return false;
+        }
+        let mut own_messages = self.own_messages.lock().unwrap();
+        if own_messages.len() == REMEMBERED_SENT_MESSAGES {
+            own_messages.pop_front();
+        }
+        own_messages.push_back(OwnMessage {
+            text: message.text.clone(),
+            message_id: None,
+        });
+        true
+    }
+
+    fn settle_echo(&self, text: &str, message_id: Option<String>) {
+        let mut own_messages = self.own_messages.lock().unwrap();
+        let Some(index) = own_messages
+            .iter()
+            .position(|own| own.message_id.is_none() && own.text == text)
+        else {
+            // already echoed
+            return;
+        };
+        match message_id.filter(|id| !id.is_empty()) {
+            Some(id) => own_messages[index].message_id = Some(id),
+            None => {
+                own_messages.remove(index);
+            }
+        }
+    }
+
+    fn send_chat_message(
+        &self,
+        channel_id: &str,
+        message: &str,
+        reply_parent_message_id: Option<&str>,
+    ) {
+        let delivery = Delivery::Chat {
+            reply_parent_message_id: reply_parent_message_id.map(str::to_string),
+        };
+        self.queue_message(channel_id, message, delivery);
+    }
+
+    fn queue_message(&self, channel_id: &str, message: &str, delivery: Delivery) {
+        for (index, part) in split_message(message, MAX_MESSAGE_CHARS)
+            .into_iter()
+            .enumerate()
+        {
+            let delivery = match &delivery {
+                Delivery::Chat { .. } if index > 0 => Delivery::Chat {
+                    reply_parent_message_id: None,
+                },
+                delivery => delivery.clone(),
+            };
+            let outgoing = OutgoingMessage {
+                channel_id: channel_id.to_string(),
+                text: part,
+                delivery,
+            };
+            match self.outgoing_tx.try_send(outgoing) {
+                Ok(()) => {}
+                Err(TrySendError::Full(outgoing)) => {
+                    log_warn!(
+                        "twitch",
+                        "Too many chat messages waiting, dropping \"{}\"",
+                        outgoing.text
+                    );
+                }
+                Err(e) => {
+                    log_error!("twitch", "Failed to queue chat message: {}", e);
+                }
+            }
+        }
+    }
+
+    async fn dispatch_messages(&self) {
+        let mut outgoing_rx = self.outgoing_rx.lock().await;
+        let mut rate_limit = RateLimit::default();
+
+        while let Some(message) = outgoing_rx.recv().await {
+            let echoed = self.expect_echo(&message);
+            let message_id = self.deliver(&message, &mut rate_limit).await;
+            if echoed {
+                self.settle_echo(&message.text, message_id);
+            }
+        }
+    }
+
+    async fn deliver(
+        &self,
+        message: &OutgoingMessage,
+        rate_limit: &mut RateLimit,
+    ) -> Option<String> {
+        for attempt in 1..=SEND_MAX_ATTEMPTS {
+            if let Some(wait) = rate_limit.wait_time(Instant::now()) {
+                log_info!(
+                    "twitch",
+                    "Chat rate limit reached, waiting {:.1}s",
+                    wait.as_secs_f32()
+                );
+                time::sleep(wait).await;
+            }
+
+            let response = match self.post_message(message).await {
+                Ok(response) => response,
+                Err(e) if is_auth_error(&e) || attempt == SEND_MAX_ATTEMPTS => {
+                    log_error!(
+                        "twitch",
+                        "Giving up on chat message \"{}\": {}",
+                        message.text,
+                        e
+                    );
+                    return None;
+                }
+                Err(e) => {
+                    log_warn!("twitch", "Failed to send chat message: {}", e);
+                    time::sleep(reconnect_delay(attempt)).await;
+                    continue;
+                }
+            };
+
+            rate_limit.update(response.headers());
+            let status = response.status();
+
+            // only chat messages report whether they were delivered, the other endpoints
+            // answer with 204 No Content
+            if status.is_success() && !matches!(message.delivery, Delivery::Chat { .. }) {
+                log_debug!("twitch", "Sent message to channel '{}'", message.channel_id);
+                return None;
+            }
+            if status.is_success() {
+                return match response.json::<HelixResponse<SentMessage>>().await {
+                    Ok(sent) => match sent.data.into_iter().next() {
+                        Some(SentMessage {
+                            message_id,
+                            is_sent: true,
+                            ..
+                        }) => {
+                            log_debug!(
+                                "twitch",
+                                "Sent message to channel '{}'",
+                                message.channel_id
+                            );
+                            Some(message_id)
+                        }
+                        Some(SentMessage {
+                            is_sent: false,
+                            drop_reason,
+                            ..
+                        }) => {
+                            let reason = drop_reason
+                                .map(|r| format!("{} ({})", r.message, r.code))
+                                .unwrap_or_else(|| "no reason given".to_string());
+                            log_warn!(
+                                "twitch",
+                                "Twitch dropped the message \"{}\": {}",
+                                message.text,
+                                reason
+                            );
+                            None
+                        }
+                        None => {
+                            log_debug!("twitch", "Send response contained no message");
+                            None
+                        }
+                    },
+                    Err(e) => {
+                        log_debug!("twitch", "Unexpected send response: {}", e);
+                        None
+                    }
+                };
+            }
+
+            let error_text = response.text().await.unwrap_or_default();
+            let retryable =
+                status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+            if !retryable || attempt == SEND_MAX_ATTEMPTS {
+                log_error!(
+                    "twitch",
+                    "Giving up on chat message \"{}\" ({}): {}",
+                    message.text,
+                    status,
+                    error_text
+                );
+                return None;
+            }
+
+            log_warn!(
+                "twitch",
+                "Sending chat message failed ({}), retrying (attempt {})",
+                status,
+                attempt
+            );
+            // a 429 comes with the time the bucket refills, which is waited for above
+            if rate_limit.wait_time(Instant::now()).is_none() {
+                time::sleep(reconnect_delay(attempt)).await;
+            }
+        }
+        None
+    }
+
+    async fn post_message(&self, message: &OutgoingMessage) -> Result<reqwest::Response, BoxError> {
+        log_debug!(
+            "twitch",
+            "Sending chat message to broadcaster: {}",
+            message.channel_id
+        );
+
+        match &message.delivery {
+            Delivery::Chat {
+                reply_parent_message_id,
+            } => {
+                let mut body = serde_json::json!({
+                    "broadcaster_id": message.channel_id,
+                    "sender_id": self.user.id,
+                    "message": message.text,
+                });
+
+                if let Some(parent_id) = reply_parent_message_id {
+                    body["reply_parent_message_id"] = serde_json::json!(parent_id);
+                }
+
+                self.send_helix(|| {
+                    self.http_client
+                        .post(endpoints().helix("chat/messages"))
+                        .json(&body)
+                })
+                .await
+            }
+            Delivery::Announcement(color) => {
+                let body = serde_json::json!({
+                    "message": message.text,
+                    "color": color,
+                });
+
+                self.send_helix(|| {
+                    self.http_client
+                        .post(endpoints().helix("chat/announcements"))
+                        .query(&[
+                            ("broadcaster_id", message.channel_id.as_str()),
+                            ("moderator_id", self.user.id.as_str()),
+                        ])
+                        .json(&body)
+                })
+                .await
+            }
+            Delivery::Whisper { user_id } => {
+                let body = serde_json::json!({ "message": message.text });
+
+                self.send_helix(|| {
+                    self.http_client
+                        .post(endpoints().helix("whispers"))
+                        .query(&[
+                            ("from_user_id", self.user.id.as_str()),
+                            ("to_user_id", user_id.as_str()),
+                        ])
+                        .json(&body)
+                })
+                .await
+            }
+        }
+    }
+}
+
+async fn init_websocket_session(url: &str) -> Result<Session, BoxError> {
+    log_debug!(
+        "twitch",
+        "Connecting to Twitch eventsub WebSocket at {}",
+        url
+    );
+
+    let (ws_stream, _response) = connect_async(url).await?;
+    log_debug!("twitch", "WebSocket connected, waiting for welcome message");
+    let (mut write, mut read) = ws_stream.split();
+
+    let welcome_text = loop {
+        log_debug!("twitch", "Waiting for message from server");
+        let msg = read
+            .next()
+            .await
+            .ok_or_else(|| -> BoxError { "Connection closed before welcome".into() })??;
+
+        match msg {
+            Message::Text(text) => {
+                log_debug!("twitch", "Received text message");
+                break text;
+            }
+            Message::Ping(data) => {
+                log_debug!("twitch", "Received ping, sending pong");
+                write.send(Message::Pong(data)).await?;
+            }
+            Message::Close(frame) => {
+                let reason = frame
+                    .as_ref()
+                    .map(|f| format!("code: {}, reason: {}", f.code, f.reason))
+                    .unwrap_or_else(|| "unknown".to_string());
+                log_debug!("twitch", "WebSocket closed immediately: {}", reason);
+                return Err(format!("WebSocket closed immediately: {}", reason).into());
+            }
+            Message::Pong(_) => {
+                log_debug!("twitch", "Received pong");
+            }
+            other => {
+                log_debug!("twitch", "Unexpected message type: {:?}", other);
+                return Err(format!("Unexpected message type: {:?}", other).into());
+            }
+        }
+    };
+
+    if welcome_text.is_empty() {
+        log_debug!("twitch", "Received empty welcome message");
+        return Err("Received empty welcome message".into());
+    }
+
+    log_debug!("twitch", "Parsing welcome message");
+    let welcome: EventMessage = serde_json::from_str(&welcome_text).map_err(|e| -> BoxError {
+        format!(
+            "Failed to parse welcome message: {}. Raw: {}",
+            e, welcome_text
+        )
+        .into()
+    })?;
+
+    if welcome.metadata.message_type != "session_welcome" {
+        log_debug!(
+            "twitch",
+            "Expected session_welcome, got: {}",
+            welcome.metadata.message_type
+        );
+        return Err("Expected session_welcome message".into());
+    }
+
+    let welcome_payload: WelcomePayload = serde_json::from_value(welcome.payload)?;
+    log_debug!("twitch", "Welcome message parsed successfully");
+
+    Ok(Session {
+        data: welcome_payload.session,
+        read: Arc::new(Mutex::new(read)),
+        write: Arc::new(Mutex::new(write)),
+    })
+}
+
+async fn get_user(
+    http_client: &reqwest::Client,
+    client_id: &str,
+    access_token: &str,
+    login: Option<&str>,
+) -> Result<TwitchUser, BoxError> {
+    log_debug!(
+        "twitch",
+        "Getting user data for {}",
+        login.unwrap_or("access token")
+    );
+    let mut request = http_client.get(endpoints().helix("users"));
+    if let Some(login) = login {
+        request = request.query(&[("login", login)]);
+    }
+    let response = request
+        .header("Authorization", format!("Bearer {}", access_token))
+        .header("Client-Id", client_id)
+        .send()
+        .await?;
+
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        let error_text = response.text().await?;
+        log_debug!("twitch", "Access token rejected: {}", error_text);
+        return Err(TwitchError::Unauthorized("Invalid or expired token".to_string()).into());
+    }
+
+    let response: TwitchResponse = response.json().await?;
+
+    if let Some(user) = response.data.first() {
+        log_debug!("twitch", "Got user: {}", user.display_name);
+        Ok(user.clone())
+    } else if let Some(login) = login {
+        Err(format!("No Twitch channel named {}", login).into())
+    } else {
+        log_debug!("twitch", "No user data in response");
+        Err("Failed to get user data".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconnect_delay_doubles_up_to_the_maximum() {
+        for (attempt, max_ms) in [
+            (1, 1000),
+            (2, 2000),
+            (3, 4000),
+            (6, 32_000),
+            (7, RECONNECT_MAX_DELAY_MS),
+            (40, RECONNECT_MAX_DELAY_MS),
+        ] {
+            let delay = reconnect_delay(attempt).as_millis() as u64;
+            assert!(
+                (max_ms / 2..=max_ms).contains(&delay),
+                "attempt {} waited {}ms",
+                attempt,
+                delay
+            );
+        }
+    }
+}
@@ -0,0 +1,522 @@
+use iced::futures::SinkExt;
+use iced::futures::channel::mpsc;
+use tokio::time::Instant;
+
+use super::cooldown::CooldownTracker;
+use super::core::{
+    Badge, ChatMessage, ChatMessageEvent, ChatMessageType, ChatbotPreferences, PreferencesUpdate,
+    TwitchEvent,
+};
+use super::message::{LongMessageMode, MAX_MESSAGE_CHARS};
+use crate::commands::{
+    AnnouncementColor, ChatRole, ControlCommand, CustomCommand, DeliveryMode, Invocation,
+    find_command, is_ignored_user,
+};
+use crate::osu::core::BeatmapData;
+use crate::osu::pp::{PpQuery, get_pp_for_query, get_pp_spread};
+use crate::placeholders::Placeholders;
+use crate::requests::{self, Enqueued, RequestSource};
+use crate::responses::{ResponseField, Responses};
+use crate::{log_debug, log_info};
+
+const TEST_BROADCASTER_ID: &str = "test-broadcaster";
+// beatmap links share the cooldowns with commands, under this name
+const REQUEST_COOLDOWN_KEY: &str = "request";
+
+#[derive(Debug, Clone)]
+pub struct BotReply {
+    pub text: String,
+    pub delivery: DeliveryMode,
+    pub announcement_color: AnnouncementColor,
+    pub parent_message_id: String,
+    pub chatter_id: String,
+}
+
+pub struct PendingRequest {
+    message_id: String,
+    chatter_id: String,
+    command: CustomCommand,
+    args: Vec<String>,
+}
+
+pub enum Dispatched {
+    Ignored,
+    Reply(BotReply),
+    Command(PendingRequest),
+}
+
+/// Decides how the bot answers chat, for both the Twitch client and the test chat.
+pub struct ChatDispatcher {
+    preferences: ChatbotPreferences,
+    cooldowns: CooldownTracker,
+    sandboxed: bool,
+}
+
+impl ChatDispatcher {
+    pub fn new(preferences: ChatbotPreferences) -> Self {
+        Self {
+            preferences,
+            cooldowns: CooldownTracker::default(),
+            sandboxed: false,
+        }
+    }
+
+    pub fn sandboxed(preferences: ChatbotPreferences) -> Self {
+        Self {
+            sandboxed: true,
+            ..Self::new(preferences)
+        }
+    }
+
+    pub fn preferences(&self) -> &ChatbotPreferences {
+        &self.preferences
+    }
+
+    /// The bot's own messages have to be filtered out beforehand.
+    pub async fn handle_message(
+        &mut self,
+        event: &ChatMessageEvent,
+        events: mpsc::Sender<TwitchEvent>,
+    ) -> Dispatched {
+        if is_ignored_user(
+            &self.preferences.ignored_users.lock().await,
+            &event.chatter_user_login,
+            &event.chatter_user_id,
+        ) {
+            log_debug!(
+                "twitch",
+                "Ignoring message from {}",
+                event.chatter_user_login
+            );
+            return Dispatched::Ignored;
+        }
+        if let Some(source) = event.shared_from()
+            && !self.preferences.shared_chat.lock().await.allows(source)
+        {
+            log_debug!(
+                "twitch",
+                "Ignoring shared message {} from {}'s chat",
+                event
+                    .source_message_id
+                    .as_deref()
+                    .unwrap_or(&event.message_id),
+                source
+            );
+            return Dispatched::Ignored;
+        }
+
+        let commands = self.preferences.commands.lock().await.clone();
+
+        if event.is_privileged()
+            && let Some(control) = Invocation::parse(&event.message.text)
+                .and_then(|invocation| ControlCommand::parse(&invocation, &commands))
+        {
+            let text = self.apply_control_command(control, events).await;
+            return Dispatched::Reply(reply_to(event, text));
+        }
+
+        if !*self.preferences.enabled.lock().await {
+            return Dispatched::Ignored;
+        }
+        let matched = Invocation::parse(&event.message.text).and_then(|invocation| {
+            find_command(&commands, &invocation).map(|command| (command.clone(), invocation))
+        });
+
+        let Some((command, invocation)) = matched else {
+            if requests::is_accepting()
+                && let Some(beatmap_id) = event.message.beatmap_link()
+            {
+                let min_role = *self.preferences.request_min_role.lock().await;
+                if let Some(blocked) = self
+                    .check_allowed(event, REQUEST_COOLDOWN_KEY, min_role)
+                    .await
+                {
+                    return blocked;
+                }
+                return Dispatched::Reply(
+                    self.handle_beatmap_request(event, beatmap_id, events).await,
+                );
+            }
+            return Dispatched::Ignored;
+        };
+
+        if let Some(blocked) = self
+            .check_allowed(event, &command.trigger, command.min_role)
+            .await
+        {
+            return blocked;
+        }
+
+        log_debug!(
+            "twitch",
+            "Received {} request from {}",
+            command.trigger,
+            event.chatter_user_name
+        );
+        Dispatched::Command(PendingRequest {
+            message_id: event.message_id.clone(),
+            chatter_id: event.chatter_user_id.clone(),
+            command,
+            args: invocation.args.iter().map(|a| a.to_string()).collect(),
+        })
+    }
+
+    async fn check_allowed(
+        &mut self,
+        event: &ChatMessageEvent,
+        key: &str,
+        min_role: ChatRole,
+    ) -> Option<Dispatched> {
+        if event.role() < min_role {
+            log_debug!(
+                "twitch",
+                "{} is not allowed to use {}, ignoring",
+                event.chatter_user_name,
+                key
+            );
+            return Some(Dispatched::Ignored);
+        }
+
+        let now = Instant::now();
+        let settings = self.preferences.cooldowns.lock().await.clone();
+        let bypass = settings.privileged_bypass && event.is_privileged();
+
+        if !bypass
+            && let Some(remaining) =
+                self.cooldowns
+                    .remaining(&settings, key, &event.chatter_user_id, now)
+        {
+            log_debug!(
+                "twitch",
+                "{} is on cooldown for {}, ignoring",
+                key,
+                event.chatter_user_name
+            );
+            if !settings.reply_on_cooldown
+                || !self
+                    .cooldowns
+                    .should_notify(key, &event.chatter_user_id, remaining, now)
+            {
+                return Some(Dispatched::Ignored);
+            }
+            let seconds = remaining.as_secs_f64().ceil().to_string();
+            let text = self.preferences.responses.lock().await.render(
+                ResponseField::OnCooldown,
+                &[("trigger", key), ("seconds", &seconds)],
+            );
+            return Some(Dispatched::Reply(reply_to(event, text)));
+        }
+
+        self.cooldowns
+            .record(&settings, key, &event.chatter_user_id, now);
+        None
+    }
+
+    pub async fn answer(
+        &self,
+        request: PendingRequest,
+        beatmap_data: Option<BeatmapData>,
+    ) -> BotReply {
+        log_debug!(
+            "twitch",
+            "Answering {} with args {:?}",
+            request.command.trigger,
+            request.args
+        );
+
+        let mode = *self.preferences.long_messages.lock().await;
+        let responses = self.preferences.responses.lock().await.clone();
+        let template = &request.command.template;
+        let text = match beatmap_data {
+            Some(mut beatmap_data)
+                if Placeholders::uses_pp(template) && !request.args.is_empty() =>
+            {
+                let args: Vec<&str> = request.args.iter().map(String::as_str).collect();
+                match PpQuery::parse(&args) {
+                    Ok(query) => {
+                        if query.mods.is_some() {
+                            beatmap_data.mods = query.mods.clone();
+                        }
+                        if query.is_single() {
+                            render_query(&query, &beatmap_data, &responses)
+                        } else {
+                            render_template(template, &beatmap_data, mode, &responses)
+                        }
+                    }
+                    Err(e) => {
+                        log_debug!("twitch", "Invalid pp arguments: {}", e);
+                        responses.render(
+                            ResponseField::InvalidPpArgument,
+                            &[
+                                ("argument", e.argument()),
+                                ("trigger", &request.command.trigger),
+                            ],
+                        )
+                    }
+                }
+            }
+            Some(beatmap_data) => render_template(template, &beatmap_data, mode, &responses),
+            None => {
+                log_debug!("twitch", "No beatmap data available");
+                responses.render(ResponseField::NoBeatmap, &[])
+            }
+        };
+
+        BotReply {
+            text,
+            delivery: request.command.delivery,
+            announcement_color: request.command.announcement_color,
+            parent_message_id: request.message_id,
+            chatter_id: request.chatter_id,
+        }
+    }
+
+    pub async fn time_out(&self, request: PendingRequest) -> BotReply {
+        let text = self
+            .preferences
+            .responses
+            .lock()
+            .await
+            .render(ResponseField::OsuTimedOut, &[]);
+        BotReply {
+            text,
+            delivery: request.command.delivery,
+            announcement_color: request.command.announcement_color,
+            parent_message_id: request.message_id,
+            chatter_id: request.chatter_id,
+        }
+    }
+
+    async fn apply_control_command(
+        &self,
+        control: ControlCommand,
+        mut events: mpsc::Sender<TwitchEvent>,
+    ) -> String {
+        let responses = self.preferences.responses.lock().await.clone();
+        let (update, reply) = match control {
+            ControlCommand::Enable(enabled) => (
+                PreferencesUpdate {
+                    enabled: Some(enabled),
+                    ..Default::default()
+                },
+                if enabled {
+                    responses.render(ResponseField::BotEnabled, &[])
+                } else {
+                    responses.render(ResponseField::BotDisabled, &[])
+                },
+            ),
+            ControlCommand::Cooldown(seconds) => {
+                let mut cooldowns = self.preferences.cooldowns.lock().await.clone();
+                cooldowns.global_seconds = seconds;
+                (
+                    PreferencesUpdate {
+                        cooldowns: Some(cooldowns),
+                        ..Default::default()
+                    },
+                    responses.render(
+                        ResponseField::CooldownSet,
+                        &[("seconds", &seconds.to_string())],
+                    ),
+                )
+            }
+            ControlCommand::SetFormat { trigger, template } => {
+                let mut commands = self.preferences.commands.lock().await.clone();
+                if let Some(command) = commands.iter_mut().find(|c| c.trigger == trigger) {
+                    command.template = template;
+                }
+                (
+                    PreferencesUpdate {
+                        commands: Some(commands),
+                        ..Default::default()
+                    },
+                    responses.render(ResponseField::FormatSet, &[("trigger", &trigger)]),
+                )
+            }
+            ControlCommand::Usage { field, trigger } => {
+                return responses.render(field, &[("trigger", &trigger)]);
+            }
+        };
+
+        if self.sandboxed {
+            return reply;
+        }
+
+        log_info!("twitch", "Changed from chat: {}", reply);
+        self.preferences.update(update.clone()).await;
+        let _ = events
+            .send(TwitchEvent::PreferencesChanged(Box::new(update)))
+            .await;
+        reply
+    }
+
+    async fn handle_beatmap_request(
+        &self,
+        event: &ChatMessageEvent,
+        beatmap_id: u32,
+        mut events: mpsc::Sender<TwitchEvent>,
+    ) -> BotReply {
+        let responses = self.preferences.responses.lock().await.clone();
+        let source = RequestSource::Chat {
+            message_id: event.message_id.clone(),
+        };
+        let enqueued = if self.sandboxed {
+            requests::preview(beatmap_id)
+        } else {
+            requests::enqueue(beatmap_id, &event.chatter_user_name, source)
+        };
+        let text = match enqueued {
+            Enqueued::Added(position) if self.sandboxed => responses.render(
+                ResponseField::RequestAdded,
+                &[("position", &position.to_string())],
+            ),
+            Enqueued::Added(position) => {
+                log_info!(
+                    "twitch",
+                    "{} requested beatmap {}",
+                    event.chatter_user_name,
+                    beatmap_id
+                );
+                let _ = events.send(TwitchEvent::RequestsChanged).await;
+                responses.render(
+                    ResponseField::RequestAdded,
+                    &[("position", &position.to_string())],
+                )
+            }
+            Enqueued::AlreadyQueued(position) => responses.render(
+                ResponseField::RequestAlreadyQueued,
+                &[("position", &position.to_string())],
+            ),
+        };
+
+        reply_to(event, text)
+    }
+}
+
+fn reply_to(event: &ChatMessageEvent, text: String) -> BotReply {
+    BotReply {
+        text,
+        delivery: DeliveryMode::Reply,
+        announcement_color: AnnouncementColor::default(),
+        parent_message_id: event.message_id.clone(),
+        chatter_id: event.chatter_user_id.clone(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TestMessage {
+    pub login: String,
+    pub roles: Vec<ChatRole>,
+    pub text: String,
+}
+
+impl TestMessage {
+    pub fn to_event(&self) -> ChatMessageEvent {
+        let chatter_id = if self.roles.contains(&ChatRole::Broadcaster) {
+            TEST_BROADCASTER_ID.to_string()
+        } else {
+            format!("test-{}", self.login.to_lowercase())
+        };
+        let badges = self
+            .roles
+            .iter()
+            .filter_map(|role| match role {
+                ChatRole::Broadcaster => Some("broadcaster"),
+                ChatRole::Moderator => Some("moderator"),
+                ChatRole::Vip => Some("vip"),
+                ChatRole::Subscriber => Some("subscriber"),
+                ChatRole::Everyone => None,
+            })
+            .map(|set_id| Badge {
+                set_id: set_id.to_string(),
+                id: "1".to_string(),
+                info: String::new(),
+            })
+            .collect();
+
+        ChatMessageEvent {
+            badges,
+            broadcaster_user_id: TEST_BROADCASTER_ID.to_string(),
+            broadcaster_user_login: "test".to_string(),
+            broadcaster_user_name: "Test".to_string(),
+            channel_points_animation_id: None,
+            channel_points_custom_reward_id: None,
+            chatter_user_id: chatter_id,
+            chatter_user_login: self.login.to_lowercase(),
+            chatter_user_name: self.login.clone(),
+            cheer: None,
+            color: String::new(),
+            is_source_only: None,
+            message: ChatMessage {
+                text: self.text.clone(),
+                fragments: Vec::new(),
+            },
+            message_id: "test-message".to_string(),
+            message_type: ChatMessageType::Text,
+            reply: None,
+            source_badges: None,
+            source_broadcaster_user_id: None,
+            source_broadcaster_user_login: None,
+            source_broadcaster_user_name: None,
+            source_message_id: None,
+        }
+    }
+}
+
+pub(super) fn render_template(
+    template: &str,
+    beatmap: &BeatmapData,
+    mode: LongMessageMode,
+    responses: &Responses,
+) -> String {
+    let render = |placeholders: Placeholders| match mode {
+        LongMessageMode::Truncate => placeholders.apply_within(template, MAX_MESSAGE_CHARS),
+        LongMessageMode::Split => placeholders.apply(template),
+    };
+
+    let placeholders = Placeholders::from_beatmap(beatmap, &responses.statuses);
+    if !Placeholders::uses_pp(template) {
+        return render(placeholders);
+    }
+
+    match get_pp_spread(
+        &beatmap.mods,
+        beatmap.osu_file_path.as_deref(),
+        beatmap.songs_folder.as_deref(),
+    ) {
+        Ok(pp_values) => render(placeholders.with_pp(&pp_values)),
+        Err(e) => {
+            log_debug!("twitch", "pp not available: {}", e);
+            responses.render(ResponseField::PpUnavailable, &[])
+        }
+    }
+}
+
+fn render_query(query: &PpQuery, beatmap: &BeatmapData, responses: &Responses) -> String {
+    let pp = match get_pp_for_query(
+        query,
+        &beatmap.mods,
+        beatmap.osu_file_path.as_deref(),
+        beatmap.songs_folder.as_deref(),
+    ) {
+        Ok(pp) => pp,
+        Err(e) => {
+            log_debug!("twitch", "pp not available: {}", e);
+            return responses.render(ResponseField::PpUnavailable, &[]);
+        }
+    };
+
+    let mut parts = Vec::new();
+    if let Some(accuracy) = query.accuracy {
+        parts.push(format!("{}%", accuracy));
+    }
+    if let Some(misses) = query.misses {
+        parts.push(format!("{}x", misses));
+    }
+    if let Some(mods) = &beatmap.mods {
+        parts.push(format!("+{}", mods.mods_string));
+    }
+    responses.render(
+        ResponseField::PpQueryResult,
+        &[("query", &parts.join(" ")), ("pp", &format!("{:.0}", pp))],
+    )
+}
@@ -0,0 +1,61 @@
+use std::env;
+use std::sync::OnceLock;
+
+const DEFAULT_CLIENT_ID: &str = env!("TWITCH_CLIENT_ID");
+const DEFAULT_EVENTSUB_URL: &str = "wss://eventsub.wss.twitch.tv/ws";
+const DEFAULT_HELIX_URL: &str = "https://api.twitch.tv/helix";
+const DEFAULT_AUTH_URL: &str = "https://id.twitch.tv/oauth2";
+
+const CLIENT_ID_VAR: &str = "OSU_TWITCHBOT_CLIENT_ID";
+const EVENTSUB_URL_VAR: &str = "OSU_TWITCHBOT_EVENTSUB_URL";
+const HELIX_URL_VAR: &str = "OSU_TWITCHBOT_HELIX_URL";
+const AUTH_URL_VAR: &str = "OSU_TWITCHBOT_AUTH_URL";
+
+static ENDPOINTS: OnceLock<TwitchEndpoints> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+pub struct TwitchEndpoints {
+    pub client_id: String,
+    pub eventsub_url: String,
+    pub helix_url: String,
+    pub auth_url: String,
+}
+
+impl TwitchEndpoints {
+    fn from_env() -> Self {
+        let var = |name: &str, default: &str| {
+            env::var(name)
+                .ok()
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| default.to_string())
+        };
+
+        Self {
+            client_id: var(CLIENT_ID_VAR, DEFAULT_CLIENT_ID),
+            eventsub_url: var(EVENTSUB_URL_VAR, DEFAULT_EVENTSUB_URL),
+            helix_url: var(HELIX_URL_VAR, DEFAULT_HELIX_URL),
+            auth_url: var(AUTH_URL_VAR, DEFAULT_AUTH_URL),
+        }
+    }
+
+    pub fn uses_default_client_id(&self) -> bool {
+        self.client_id == DEFAULT_CLIENT_ID
+    }
+
+    pub fn helix(&self, path: &str) -> String {
+        format!("{}/{}", self.helix_url.trim_end_matches('/'), path)
+    }
+
+    pub fn auth(&self, path: &str) -> String {
+        format!("{}/{}", self.auth_url.trim_end_matches('/'), path)
+    }
+}
+
+/// Fails, handing the endpoints back, once they've been read or set.
+pub fn set_endpoints(endpoints: TwitchEndpoints) -> Result<(), TwitchEndpoints> {
+    ENDPOINTS.set(endpoints)
+}
+
+pub fn endpoints() -> &'static TwitchEndpoints {
+    ENDPOINTS.get_or_init(TwitchEndpoints::from_env)
+}
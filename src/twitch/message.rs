@@ -0,0 +1,94 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::text::truncate_chars;
+
+pub const MAX_MESSAGE_CHARS: usize = 500;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LongMessageMode {
+    #[default]
+    Truncate,
+    Split,
+}
+
+impl LongMessageMode {
+    pub const ALL: [LongMessageMode; 2] = [LongMessageMode::Truncate, LongMessageMode::Split];
+}
+
+impl Display for LongMessageMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LongMessageMode::Truncate => write!(f, "Shorten names"),
+            LongMessageMode::Split => write!(f, "Split into several messages"),
+        }
+    }
+}
+
+pub fn split_message(message: &str, max_chars: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut current_chars = 0;
+
+    for word in message.split_whitespace() {
+        let mut word = word;
+        let mut word_chars = word.chars().count();
+
+        if current_chars > 0 && current_chars + 1 + word_chars > max_chars {
+            parts.push(std::mem::take(&mut current));
+            current_chars = 0;
+        }
+
+        // words that don't fit into a message of their own are cut wherever they hit the limit
+        while word_chars > max_chars {
+            let head = truncate_chars(word, max_chars);
+            parts.push(head.to_string());
+            word = &word[head.len()..];
+            word_chars -= max_chars;
+        }
+
+        if word.is_empty() {
+            continue;
+        }
+        if current_chars > 0 {
+            current.push(' ');
+            current_chars += 1;
+        }
+        current.push_str(word);
+        current_chars += word_chars;
+    }
+
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_short_messages_whole() {
+        assert_eq!(split_message("hello  world", 20), ["hello world"]);
+        assert!(split_message("   ", 20).is_empty());
+    }
+
+    #[test]
+    fn splits_between_words() {
+        assert_eq!(split_message("aaaa bbbb cccc", 9), ["aaaa bbbb", "cccc"]);
+    }
+
+    #[test]
+    fn cuts_words_longer_than_a_message() {
+        assert_eq!(split_message("ab abcdefgh", 4), ["ab", "abcd", "efgh"]);
+    }
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        let parts = split_message("ééé ééé", 3);
+        assert_eq!(parts, ["ééé", "ééé"]);
+    }
+}
@@ -0,0 +1,982 @@
+//! In-process stand-in for Twitch's EventSub websocket, Helix and OAuth endpoints, so the
+//! whole command round-trip can be exercised without network access. Started with
+//! `--mock-twitch`; every line typed on stdin is delivered as a chat message from a mock
+//! viewer, `/shared <text>` sends one shared from a partner channel's Shared Chat,
+//! `/redeem <input>` redeems the mock Channel Points reward, `/reconnect` makes the
+//! server migrate the session, `/drop` cuts the connection and `/revoke` revokes the
+//! subscriptions. Chat messages the bot sends are echoed back, as Twitch does. Tests
+//! drive it through a [`MockTwitch`] instead.
+
+use std::collections::HashSet;
+use std::io::{self, BufRead};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use iced::futures::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::{self, Duration};
+use tokio_tungstenite::accept_async;
+use tokio_tungstenite::tungstenite::Message;
+
+use super::auth::login_scopes;
+use super::core::REDEMPTION_SUBSCRIPTION;
+use super::endpoints::{TwitchEndpoints, set_endpoints};
+use crate::{log_debug, log_error, log_info, log_warn};
+
+const MOCK_CLIENT_ID: &str = "mock-client-id";
+const MOCK_SESSION_ID: &str = "mock-session";
+const MOCK_KEEPALIVE_SECONDS: u64 = 10;
+const MOCK_TOKEN_EXPIRY_SECONDS: u64 = 4 * 3600;
+const MOCK_BROADCASTER_ID: &str = "1000";
+const MOCK_BROADCASTER_LOGIN: &str = "mock_streamer";
+const MOCK_VIEWER_ID: &str = "2000";
+const MOCK_VIEWER_LOGIN: &str = "mock_viewer";
+const MOCK_PARTNER_ID: &str = "3000";
+const MOCK_PARTNER_LOGIN: &str = "mock_partner";
+const MOCK_REWARD_ID: &str = "mock-reward";
+const MOCK_RATE_LIMIT: u64 = 800;
+// Twitch often echoes a chat message before answering the request that sent it
+const MOCK_ECHO_LEAD_MILLIS: u64 = 100;
+
+static MESSAGE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone)]
+pub enum MockEvent {
+    ChatMessage(String),
+    SharedChatMessage(String),
+    Redemption(String),
+    Reconnect,
+    Drop,
+    Revoke,
+}
+
+enum Outgoing {
+    Text(String),
+    Drop,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct SentChatMessage {
+    pub text: String,
+    pub reply_parent_message_id: Option<String>,
+}
+
+#[derive(Default)]
+struct Sessions {
+    next_id: u64,
+    active: Option<(u64, mpsc::UnboundedSender<Outgoing>)>,
+    replay: Option<Vec<String>>,
+    retired: HashSet<u64>,
+}
+
+#[derive(Clone)]
+pub struct MockTwitch {
+    sessions: Arc<Mutex<Sessions>>,
+    addr: SocketAddr,
+    sent: broadcast::Sender<SentChatMessage>,
+}
+
+impl MockTwitch {
+    pub fn send(&self, event: MockEvent) {
+        let message = match event {
+            MockEvent::ChatMessage(text) => {
+                notification("channel.chat.message", chat_event_json(&text))
+            }
+            MockEvent::SharedChatMessage(text) => {
+                notification("channel.chat.message", shared_chat_event_json(&text))
+            }
+            MockEvent::Redemption(input) => {
+                notification(REDEMPTION_SUBSCRIPTION, redemption_event_json(&input))
+            }
+            MockEvent::Reconnect => {
+                let mut sessions = self.sessions.lock().unwrap();
+                let Some((id, session)) = sessions.active.clone() else {
+                    return;
+                };
+                let reconnect_url = format!("ws://{}/ws", self.addr);
+                let _ = session.send(Outgoing::Text(eventsub_message(
+                    "session_reconnect",
+                    None,
+                    json!({ "session": session_json("reconnecting", Some(&reconnect_url)) }),
+                )));
+                sessions.retired.insert(id);
+                sessions.replay = Some(Vec::new());
+                return;
+            }
+            MockEvent::Drop => {
+                if let Some((_, session)) = &self.sessions.lock().unwrap().active {
+                    let _ = session.send(Outgoing::Drop);
+                }
+                return;
+            }
+            MockEvent::Revoke => {
+                let mut subscription = subscription_json("channel.chat.message");
+                subscription["status"] = json!("authorization_revoked");
+                eventsub_message(
+                    "revocation",
+                    Some("channel.chat.message"),
+                    json!({ "subscription": subscription }),
+                )
+            }
+        };
+
+        self.notify(message);
+    }
+
+    fn notify(&self, message: String) {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some((_, session)) = &sessions.active {
+            let _ = session.send(Outgoing::Text(message.clone()));
+        }
+        if let Some(replay) = &mut sessions.replay {
+            replay.push(message);
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn retired_sessions(&self) -> usize {
+        self.sessions.lock().unwrap().retired.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn sent_messages(&self) -> broadcast::Receiver<SentChatMessage> {
+        self.sent.subscribe()
+    }
+}
+
+pub fn spawn() -> io::Result<()> {
+    #[cfg(all(target_os = "windows", not(debug_assertions)))]
+    alloc_console();
+
+    let mock = start()?;
+    std::thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let event = if line == "/reconnect" {
+                MockEvent::Reconnect
+            } else if line == "/drop" {
+                MockEvent::Drop
+            } else if line == "/revoke" {
+                MockEvent::Revoke
+            } else if let Some(text) = line.strip_prefix("/shared") {
+                MockEvent::SharedChatMessage(text.trim().to_string())
+            } else if let Some(input) = line.strip_prefix("/redeem") {
+                MockEvent::Redemption(input.trim().to_string())
+            } else {
+                MockEvent::ChatMessage(line.to_string())
+            };
+            mock.send(event);
+        }
+    });
+
+    log_info!(
+        "mock",
+        "Type a line to send it as chat, /shared <text> to send it from a Shared Chat partner, /redeem <input> to redeem a reward, /reconnect to migrate the session, /drop to cut the connection, /revoke to revoke the subscriptions"
+    );
+    Ok(())
+}
+
+#[cfg(all(target_os = "windows", not(debug_assertions)))]
+fn alloc_console() {
+    use windows::Win32::System::Console::AllocConsole;
+    unsafe {
+        let _ = AllocConsole();
+    }
+}
+
+pub fn start() -> io::Result<MockTwitch> {
+    let ws_listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let http_listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    ws_listener.set_nonblocking(true)?;
+    http_listener.set_nonblocking(true)?;
+
+    let ws_addr = ws_listener.local_addr()?;
+    let http_addr = http_listener.local_addr()?;
+
+    set_endpoints(TwitchEndpoints {
+        client_id: MOCK_CLIENT_ID.to_string(),
+        eventsub_url: format!("ws://{}/ws", ws_addr),
+        helix_url: format!("http://{}/helix", http_addr),
+        auth_url: format!("http://{}/oauth2", http_addr),
+    })
+    .map_err(|_| io::Error::other("Twitch endpoints are already in use"))?;
+
+    let (sent_tx, _) = broadcast::channel::<SentChatMessage>(32);
+    let mock = MockTwitch {
+        sessions: Arc::default(),
+        addr: ws_addr,
+        sent: sent_tx,
+    };
+    let server = mock.clone();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    std::thread::spawn(move || {
+        runtime.block_on(async move {
+            let (Ok(ws_listener), Ok(http_listener)) = (
+                TcpListener::from_std(ws_listener),
+                TcpListener::from_std(http_listener),
+            ) else {
+                log_error!("mock", "Failed to start mock listeners");
+                return;
+            };
+
+            tokio::spawn(run_http_server(http_listener, server.clone()));
+            run_ws_server(ws_listener, server.sessions).await;
+        });
+    });
+
+    log_info!(
+        "mock",
+        "Mock Twitch running (eventsub: {}, http: {})",
+        ws_addr,
+        http_addr
+    );
+    Ok(mock)
+}
+
+async fn run_ws_server(listener: TcpListener, sessions: Arc<Mutex<Sessions>>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(handle_ws_connection(stream, Arc::clone(&sessions)));
+            }
+            Err(e) => {
+                log_warn!("mock", "Failed to accept websocket connection: {}", e);
+            }
+        }
+    }
+}
+
+async fn handle_ws_connection(stream: TcpStream, sessions: Arc<Mutex<Sessions>>) {
+    let ws = match accept_async(stream).await {
+        Ok(ws) => ws,
+        Err(e) => {
+            log_warn!("mock", "Websocket handshake failed: {}", e);
+            return;
+        }
+    };
+    let (mut write, mut read) = ws.split();
+    log_debug!("mock", "Websocket client connected");
+
+    let (outgoing_tx, mut outgoing) = mpsc::unbounded_channel();
+    // registered before the welcome, so nothing sent once the client is connected is missed
+    let id = {
+        let mut sessions = sessions.lock().unwrap();
+        let id = sessions.next_id;
+        sessions.next_id += 1;
+        for message in sessions.replay.take().unwrap_or_default() {
+            let _ = outgoing_tx.send(Outgoing::Text(message));
+        }
+        sessions.active = Some((id, outgoing_tx.clone()));
+        id
+    };
+
+    let welcome = eventsub_message(
+        "session_welcome",
+        None,
+        json!({ "session": session_json("connected", None) }),
+    );
+    if write.send(Message::Text(welcome)).await.is_err() {
+        close_session(&sessions, id);
+        return;
+    }
+
+    let mut keepalive = time::interval(Duration::from_secs(MOCK_KEEPALIVE_SECONDS));
+    keepalive.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = keepalive.tick() => {
+                let message = eventsub_message("session_keepalive", None, json!({}));
+                if write.send(Message::Text(message)).await.is_err() {
+                    break;
+                }
+            }
+            Some(outgoing) = outgoing.recv() => match outgoing {
+                Outgoing::Text(message) => {
+                    if write.send(Message::Text(message)).await.is_err() {
+                        break;
+                    }
+                }
+                Outgoing::Drop => break,
+            },
+            msg = read.next() => match msg {
+                Some(Ok(Message::Ping(data))) => {
+                    let _ = write.send(Message::Pong(data)).await;
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            }
+        }
+    }
+
+    close_session(&sessions, id);
+    log_debug!("mock", "Websocket client disconnected");
+}
+
+fn close_session(sessions: &Mutex<Sessions>, id: u64) {
+    let mut sessions = sessions.lock().unwrap();
+    sessions.retired.remove(&id);
+    if sessions
+        .active
+        .as_ref()
+        .is_some_and(|(active, _)| *active == id)
+    {
+        sessions.active = None;
+    }
+}
+
+async fn run_http_server(listener: TcpListener, mock: MockTwitch) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let mock = mock.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_http_connection(stream, &mock).await {
+                        log_warn!("mock", "HTTP request failed: {}", e);
+                    }
+                });
+            }
+            Err(e) => {
+                log_warn!("mock", "Failed to accept HTTP connection: {}", e);
+            }
+        }
+    }
+}
+
+async fn handle_http_connection(mut stream: TcpStream, mock: &MockTwitch) -> io::Result<()> {
+    let (read_half, mut write_half) = stream.split();
+    let mut reader = BufReader::new(read_half);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).await?;
+    let body = String::from_utf8_lossy(&body);
+
+    let path = target.split('?').next().unwrap_or_default();
+    let (status, response) = route(&method, path, &body, mock);
+    if path == "/helix/chat/messages" {
+        time::sleep(Duration::from_millis(MOCK_ECHO_LEAD_MILLIS)).await;
+    }
+    let response = match status {
+        204 => String::new(),
+        _ => response.to_string(),
+    };
+    let reason = match status {
+        200 => "OK",
+        202 => "Accepted",
+        204 => "No Content",
+        _ => "Not Found",
+    };
+
+    // the bucket never runs out, the headers are only there to be parsed
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nRatelimit-Limit: {}\r\nRatelimit-Remaining: {}\r\nRatelimit-Reset: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason,
+        response.len(),
+        MOCK_RATE_LIMIT,
+        MOCK_RATE_LIMIT - 1,
+        chrono::Utc::now().timestamp() + 60
+    );
+    write_half.write_all(head.as_bytes()).await?;
+    write_half.write_all(response.as_bytes()).await?;
+    write_half.flush().await
+}
+
+fn route(method: &str, path: &str, body: &str, mock: &MockTwitch) -> (u16, Value) {
+    log_debug!("mock", "{} {}", method, path);
+    let body: Value = serde_json::from_str(body).unwrap_or(Value::Null);
+
+    match (method, path) {
+        ("GET", "/oauth2/validate") => (
+            200,
+            json!({
+                "client_id": MOCK_CLIENT_ID,
+                "login": MOCK_BROADCASTER_LOGIN,
+                "user_id": MOCK_BROADCASTER_ID,
+                "scopes": login_scopes(),
+                "expires_in": MOCK_TOKEN_EXPIRY_SECONDS,
+            }),
+        ),
+        ("POST", "/oauth2/device") => (
+            200,
+            json!({
+                "device_code": "mock-device-code",
+                "user_code": "MOCKCODE",
+                "verification_uri": "https://www.twitch.tv/activate",
+                "expires_in": 1800,
+                "interval": 1,
+            }),
+        ),
+        ("POST", "/oauth2/token") => (
+            200,
+            json!({
+                "access_token": format!("mock-access-token-{}", next_id()),
+                "refresh_token": format!("mock-refresh-token-{}", next_id()),
+                "expires_in": MOCK_TOKEN_EXPIRY_SECONDS,
+                "scope": login_scopes(),
+                "token_type": "bearer",
+            }),
+        ),
+        ("GET", "/helix/users") => (200, json!({ "data": [user_json()] })),
+        ("POST", "/helix/eventsub/subscriptions") => {
+            let sub_type = body["type"].as_str().unwrap_or_default();
+            log_info!("mock", "Subscribed to {}", sub_type);
+            (
+                202,
+                json!({
+                    "data": [subscription_json(sub_type)],
+                    "total": 1,
+                    "total_cost": 0,
+                    "max_total_cost": 10,
+                }),
+            )
+        }
+        ("GET", "/helix/channel_points/custom_rewards") => (
+            200,
+            json!({
+                "data": [{
+                    "id": MOCK_REWARD_ID,
+                    "title": "Request a beatmap",
+                    "cost": 1000,
+                    "is_user_input_required": true,
+                }]
+            }),
+        ),
+        ("POST", "/helix/channel_points/custom_rewards") => {
+            let title = body["title"].as_str().unwrap_or_default();
+            log_info!("mock", "Created reward {}", title);
+            (
+                200,
+                json!({
+                    "data": [{
+                        "id": format!("mock-reward-{}", next_id()),
+                        "title": title,
+                        "cost": body["cost"],
+                        "is_user_input_required": body["is_user_input_required"],
+                    }]
+                }),
+            )
+        }
+        ("PATCH", "/helix/channel_points/custom_rewards/redemptions") => {
+            let status = body["status"].as_str().unwrap_or_default();
+            log_info!("mock", "Redemption marked as {}", status);
+            (200, json!({ "data": [{ "status": status }] }))
+        }
+        ("POST", "/helix/chat/announcements") => {
+            log_info!(
+                "mock",
+                "Bot announced ({}): {}",
+                body["color"].as_str().unwrap_or_default(),
+                body["message"].as_str().unwrap_or_default()
+            );
+            (204, Value::Null)
+        }
+        ("POST", "/helix/whispers") => {
+            log_info!(
+                "mock",
+                "Bot whispered: {}",
+                body["message"].as_str().unwrap_or_default()
+            );
+            (204, Value::Null)
+        }
+        ("POST", "/helix/chat/messages") => {
+            let message = body["message"].as_str().unwrap_or_default();
+            log_info!("mock", "Bot sent: {}", message);
+            let _ = mock.sent.send(SentChatMessage {
+                text: message.to_string(),
+                reply_parent_message_id: body["reply_parent_message_id"]
+                    .as_str()
+                    .map(str::to_string),
+            });
+            let message_id = format!("mock-sent-{}", next_id());
+            mock.notify(notification(
+                "channel.chat.message",
+                own_chat_event_json(message, &message_id),
+            ));
+            (
+                200,
+                json!({
+                    "data": [{
+                        "message_id": message_id,
+                        "is_sent": true,
+                    }]
+                }),
+            )
+        }
+        _ => (
+            404,
+            json!({
+                "error": "Not Found",
+                "status": 404,
+                "message": format!("mock has no route for {} {}", method, path),
+            }),
+        ),
+    }
+}
+
+fn next_id() -> u64 {
+    MESSAGE_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+fn timestamp() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+fn eventsub_message(message_type: &str, subscription_type: Option<&str>, payload: Value) -> String {
+    let mut metadata = json!({
+        "message_id": format!("mock-{}", next_id()),
+        "message_type": message_type,
+        "message_timestamp": timestamp(),
+    });
+    if let Some(sub_type) = subscription_type {
+        metadata["subscription_type"] = json!(sub_type);
+        metadata["subscription_version"] = json!("1");
+    }
+
+    json!({ "metadata": metadata, "payload": payload }).to_string()
+}
+
+fn notification(subscription_type: &str, event: Value) -> String {
+    eventsub_message(
+        "notification",
+        Some(subscription_type),
+        json!({
+            "subscription": subscription_json(subscription_type),
+            "event": event,
+        }),
+    )
+}
+
+fn session_json(status: &str, reconnect_url: Option<&str>) -> Value {
+    json!({
+        "id": MOCK_SESSION_ID,
+        "status": status,
+        "connected_at": timestamp(),
+        "keepalive_timeout_seconds": MOCK_KEEPALIVE_SECONDS,
+        "reconnect_url": reconnect_url,
+    })
+}
+
+fn subscription_json(sub_type: &str) -> Value {
+    json!({
+        "id": format!("mock-sub-{}", sub_type),
+        "status": "enabled",
+        "type": sub_type,
+        "version": "1",
+        "condition": {
+            "broadcaster_user_id": MOCK_BROADCASTER_ID,
+            "user_id": MOCK_BROADCASTER_ID,
+        },
+        "transport": { "method": "websocket", "session_id": MOCK_SESSION_ID },
+        "created_at": timestamp(),
+        "cost": 0,
+    })
+}
+
+fn user_json() -> Value {
+    json!({
+        "id": MOCK_BROADCASTER_ID,
+        "login": MOCK_BROADCASTER_LOGIN,
+        "display_name": MOCK_BROADCASTER_LOGIN,
+        "type": "",
+        "broadcaster_type": "",
+        "description": "",
+        "profile_image_url": "",
+        "offline_image_url": "",
+        "created_at": timestamp(),
+    })
+}
+
+fn chat_event_json(text: &str) -> Value {
+    json!({
+        "broadcaster_user_id": MOCK_BROADCASTER_ID,
+        "broadcaster_user_login": MOCK_BROADCASTER_LOGIN,
+        "broadcaster_user_name": MOCK_BROADCASTER_LOGIN,
+        "chatter_user_id": MOCK_VIEWER_ID,
+        "chatter_user_login": MOCK_VIEWER_LOGIN,
+        "chatter_user_name": MOCK_VIEWER_LOGIN,
+        "message_id": format!("mock-chat-{}", next_id()),
+        "message": {
+            "text": text,
+            "fragments": [{
+                "type": "text",
+                "text": text,
+                "cheermote": null,
+                "emote": null,
+                "mention": null,
+            }],
+        },
+        "color": "#9146FF",
+        "badges": [],
+        "message_type": "text",
+        "cheer": null,
+        "reply": null,
+        "channel_points_custom_reward_id": null,
+        "channel_points_animation_id": null,
+    })
+}
+
+fn own_chat_event_json(text: &str, message_id: &str) -> Value {
+    let mut event = chat_event_json(text);
+    event["chatter_user_id"] = json!(MOCK_BROADCASTER_ID);
+    event["chatter_user_login"] = json!(MOCK_BROADCASTER_LOGIN);
+    event["chatter_user_name"] = json!(MOCK_BROADCASTER_LOGIN);
+    event["message_id"] = json!(message_id);
+    event
+}
+
+fn shared_chat_event_json(text: &str) -> Value {
+    let mut event = chat_event_json(text);
+    event["source_broadcaster_user_id"] = json!(MOCK_PARTNER_ID);
+    event["source_broadcaster_user_login"] = json!(MOCK_PARTNER_LOGIN);
+    event["source_broadcaster_user_name"] = json!(MOCK_PARTNER_LOGIN);
+    event["source_message_id"] = json!(format!("mock-partner-chat-{}", next_id()));
+    event["source_badges"] = json!([]);
+    event
+}
+
+fn redemption_event_json(input: &str) -> Value {
+    json!({
+        "id": format!("mock-redemption-{}", next_id()),
+        "broadcaster_user_id": MOCK_BROADCASTER_ID,
+        "broadcaster_user_login": MOCK_BROADCASTER_LOGIN,
+        "broadcaster_user_name": MOCK_BROADCASTER_LOGIN,
+        "user_id": MOCK_VIEWER_ID,
+        "user_login": MOCK_VIEWER_LOGIN,
+        "user_name": MOCK_VIEWER_LOGIN,
+        "user_input": input,
+        "status": "unfulfilled",
+        "reward": {
+            "id": MOCK_REWARD_ID,
+            "title": "Request a beatmap",
+            "cost": 1000,
+            "prompt": "Paste a beatmap link",
+        },
+        "redeemed_at": timestamp(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, OnceLock};
+
+    use iced::futures::channel::mpsc;
+    use tokio::sync::MutexGuard;
+    use tokio::task::JoinHandle;
+    use tokio::time::timeout;
+
+    use super::*;
+    use crate::commands::{DEFAULT_NP_COMMAND, default_commands};
+    use crate::osu::core::{MemoryEvent, OsuCommand};
+    use crate::requests;
+    use crate::responses::Responses;
+    use crate::twitch::cooldown::CooldownSettings;
+    use crate::twitch::core::{ChatbotSettings, PreferencesUpdate, TwitchClient, TwitchEvent};
+    use crate::twitch::shared_chat::{SharedChatMode, SharedChatSettings};
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    // the endpoints can only be set once per process, so the tests share one mock and
+    // take turns using it
+    static MOCK: OnceLock<MockTwitch> = OnceLock::new();
+    static TURN: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    struct Bot {
+        mock: MockTwitch,
+        client: Arc<TwitchClient>,
+        sent: broadcast::Receiver<SentChatMessage>,
+        osu_commands: mpsc::Receiver<OsuCommand>,
+        osu_events: mpsc::Sender<MemoryEvent>,
+        events: mpsc::Receiver<TwitchEvent>,
+        runner: JoinHandle<()>,
+        _turn: MutexGuard<'static, ()>,
+    }
+
+    impl Bot {
+        async fn connect(settings: ChatbotSettings) -> Self {
+            let turn = TURN.lock().await;
+            let mock = MOCK
+                .get_or_init(|| start().expect("mock should start"))
+                .clone();
+            let sent = mock.sent_messages();
+
+            let client = TwitchClient::new("mock-token", settings, "")
+                .await
+                .expect("client should connect to the mock");
+            client
+                .subscribe_to_channel_messages(&client.channel.id)
+                .await
+                .expect("subscription should succeed");
+
+            let (osu_tx, osu_commands) = mpsc::channel(10);
+            let (osu_events, osu_rx) = mpsc::channel(10);
+            let (events_tx, events) = mpsc::channel(100);
+            let client = Arc::new(client);
+            let runner = Arc::clone(&client);
+            let runner =
+                tokio::spawn(async move { runner.run_supervised(osu_tx, osu_rx, events_tx).await });
+
+            Self {
+                mock,
+                client,
+                sent,
+                osu_commands,
+                osu_events,
+                events,
+                runner,
+                _turn: turn,
+            }
+        }
+
+        async fn beatmap_request(&mut self) -> u64 {
+            match timeout(TIMEOUT, self.osu_commands.next()).await {
+                Ok(Some(OsuCommand::RequestBeatmapData(request_id))) => request_id,
+                _ => panic!("the command should ask the osu worker for the beatmap"),
+            }
+        }
+
+        async fn assert_no_beatmap_request(&mut self, wait: Duration) {
+            if let Ok(Some(command)) = timeout(wait, self.osu_commands.next()).await {
+                panic!("unexpected osu command: {:?}", command);
+            }
+        }
+
+        async fn answer(&mut self, request_id: u64) {
+            self.osu_events
+                .send(MemoryEvent::BeatmapDataResponse(request_id, None))
+                .await
+                .unwrap();
+        }
+
+        async fn expect_event(&mut self, expected: impl Fn(&TwitchEvent) -> bool) {
+            timeout(TIMEOUT, async {
+                while let Some(event) = self.events.next().await {
+                    if expected(&event) {
+                        return;
+                    }
+                }
+                panic!("the client stopped sending events");
+            })
+            .await
+            .expect("the client should send the event in time");
+        }
+
+        async fn reply(&mut self) -> SentChatMessage {
+            timeout(TIMEOUT, self.sent.recv())
+                .await
+                .expect("the bot should answer in time")
+                .unwrap()
+        }
+    }
+
+    fn settings() -> ChatbotSettings {
+        ChatbotSettings {
+            enabled: true,
+            commands: default_commands(),
+            cooldowns: CooldownSettings {
+                global_seconds: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn threads_onto_channel_message(reply: &SentChatMessage) -> bool {
+        reply
+            .reply_parent_message_id
+            .as_deref()
+            .is_some_and(|id| id.starts_with("mock-chat-"))
+    }
+
+    #[tokio::test]
+    async fn command_round_trip() {
+        let mut bot = Bot::connect(settings()).await;
+
+        bot.mock
+            .send(MockEvent::ChatMessage(DEFAULT_NP_COMMAND.to_string()));
+        let request_id = bot.beatmap_request().await;
+        bot.answer(request_id).await;
+        let reply = bot.reply().await;
+        assert_eq!(reply.text, Responses::default().no_beatmap);
+        assert!(threads_onto_channel_message(&reply));
+
+        // shared messages are replied to by their id in this channel, not the partner's
+        bot.client
+            .update_preferences(PreferencesUpdate {
+                shared_chat: Some(SharedChatSettings {
+                    mode: SharedChatMode::AllChannels,
+                    channels: Vec::new(),
+                }),
+                ..Default::default()
+            })
+            .await;
+        bot.mock
+            .send(MockEvent::SharedChatMessage(DEFAULT_NP_COMMAND.to_string()));
+        let request_id = bot.beatmap_request().await;
+        bot.answer(request_id).await;
+        assert!(threads_onto_channel_message(&bot.reply().await));
+    }
+
+    #[tokio::test]
+    async fn notifications_around_a_reconnect_are_handled_once() {
+        let mut bot = Bot::connect(settings()).await;
+
+        bot.mock.send(MockEvent::Reconnect);
+        bot.mock
+            .send(MockEvent::ChatMessage(DEFAULT_NP_COMMAND.to_string()));
+
+        let request_id = bot.beatmap_request().await;
+        bot.answer(request_id).await;
+        bot.reply().await;
+        bot.assert_no_beatmap_request(Duration::from_secs(1)).await;
+
+        timeout(TIMEOUT, async {
+            while bot.mock.retired_sessions() > 0 {
+                time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("the old session should be closed");
+    }
+
+    #[tokio::test]
+    async fn reconnects_until_authorization_is_revoked() {
+        let mut bot = Bot::connect(settings()).await;
+
+        bot.mock.send(MockEvent::Drop);
+        bot.expect_event(|e| matches!(e, TwitchEvent::Reconnecting(1)))
+            .await;
+        bot.expect_event(|e| matches!(e, TwitchEvent::Connected(_)))
+            .await;
+
+        bot.mock
+            .send(MockEvent::ChatMessage(DEFAULT_NP_COMMAND.to_string()));
+        let request_id = bot.beatmap_request().await;
+        bot.answer(request_id).await;
+        bot.reply().await;
+
+        bot.mock.send(MockEvent::Revoke);
+        bot.expect_event(|e| matches!(e, TwitchEvent::Error(_)))
+            .await;
+        timeout(TIMEOUT, bot.runner)
+            .await
+            .expect("the client should give up")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn answers_commands_that_arrive_together() {
+        let mut bot = Bot::connect(settings()).await;
+
+        bot.mock
+            .send(MockEvent::ChatMessage(DEFAULT_NP_COMMAND.to_string()));
+        bot.mock
+            .send(MockEvent::ChatMessage(DEFAULT_NP_COMMAND.to_string()));
+        let first = bot.beatmap_request().await;
+        let second = bot.beatmap_request().await;
+        bot.answer(second).await;
+        bot.answer(first).await;
+
+        let replies = [bot.reply().await, bot.reply().await];
+        assert!(
+            replies
+                .iter()
+                .all(|reply| reply.text == Responses::default().no_beatmap)
+        );
+        assert_ne!(
+            replies[0].reply_parent_message_id,
+            replies[1].reply_parent_message_id
+        );
+
+        // a command osu! never answers gets a reply once it expires, and none after that
+        bot.mock
+            .send(MockEvent::ChatMessage(DEFAULT_NP_COMMAND.to_string()));
+        let unanswered = bot.beatmap_request().await;
+        assert_eq!(bot.reply().await.text, Responses::default().osu_timed_out);
+        bot.answer(unanswered).await;
+        assert!(
+            timeout(Duration::from_millis(500), bot.sent.recv())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn refunds_redemptions_while_the_bot_is_off() {
+        let mut bot = Bot::connect(ChatbotSettings {
+            enabled: false,
+            request_reward_id: MOCK_REWARD_ID.to_string(),
+            ..settings()
+        })
+        .await;
+        requests::set_accepting(true);
+
+        bot.mock.send(MockEvent::Redemption(
+            "https://osu.ppy.sh/b/129891".to_string(),
+        ));
+        let reply = bot.reply().await;
+        assert_eq!(
+            reply.text,
+            format!(
+                "@{} {}",
+                MOCK_VIEWER_LOGIN,
+                Responses::default().redemption_closed
+            )
+        );
+        requests::set_accepting(false);
+    }
+
+    #[tokio::test]
+    async fn ignores_its_own_message_echoed_before_it_was_sent() {
+        // a reply that is itself a command would answer itself if its echo went unnoticed
+        let mut bot = Bot::connect(ChatbotSettings {
+            responses: Responses {
+                no_beatmap: DEFAULT_NP_COMMAND.to_string(),
+                ..Default::default()
+            },
+            ..settings()
+        })
+        .await;
+
+        bot.mock
+            .send(MockEvent::ChatMessage(DEFAULT_NP_COMMAND.to_string()));
+        let request_id = bot.beatmap_request().await;
+        bot.answer(request_id).await;
+
+        assert_eq!(bot.reply().await.text, DEFAULT_NP_COMMAND);
+        bot.assert_no_beatmap_request(Duration::from_millis(500))
+            .await;
+    }
+}
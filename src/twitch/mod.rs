@@ -0,0 +1,10 @@
+pub mod announce;
+pub mod auth;
+pub mod cooldown;
+pub mod core;
+pub mod dispatch;
+pub mod endpoints;
+pub mod message;
+pub mod mock;
+pub mod ratelimit;
+pub mod shared_chat;
@@ -0,0 +1,83 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use reqwest::header::HeaderMap;
+use tokio::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+pub struct RateLimit {
+    remaining: Option<u64>,
+    reset_at: Option<Instant>,
+}
+
+impl RateLimit {
+    pub fn update(&mut self, headers: &HeaderMap) {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+        };
+
+        if let Some(remaining) = header("Ratelimit-Remaining") {
+            self.remaining = Some(remaining);
+        }
+        // the reset time is a unix timestamp, which is turned into an instant so it isn't
+        // affected by the system clock changing
+        if let Some(reset) = header("Ratelimit-Reset") {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            self.reset_at = Some(Instant::now() + Duration::from_secs(reset.saturating_sub(now)));
+        }
+    }
+
+    pub fn wait_time(&self, now: Instant) -> Option<Duration> {
+        if self.remaining != Some(0) {
+            return None;
+        }
+        let wait = self.reset_at?.saturating_duration_since(now);
+        (!wait.is_zero()).then_some(wait)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_only_when_the_bucket_is_empty() {
+        let now = Instant::now();
+        let mut rate_limit = RateLimit {
+            remaining: Some(1),
+            reset_at: Some(now + Duration::from_secs(5)),
+        };
+        assert_eq!(rate_limit.wait_time(now), None);
+
+        rate_limit.remaining = Some(0);
+        assert_eq!(rate_limit.wait_time(now), Some(Duration::from_secs(5)));
+        assert_eq!(rate_limit.wait_time(now + Duration::from_secs(5)), None);
+    }
+
+    #[test]
+    fn does_not_wait_without_headers() {
+        assert_eq!(RateLimit::default().wait_time(Instant::now()), None);
+    }
+
+    #[test]
+    fn reads_the_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Ratelimit-Remaining", "0".parse().unwrap());
+        let reset = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 30;
+        headers.insert("Ratelimit-Reset", reset.to_string().parse().unwrap());
+
+        let mut rate_limit = RateLimit::default();
+        rate_limit.update(&headers);
+        let wait = rate_limit.wait_time(Instant::now()).unwrap();
+        assert!(wait > Duration::from_secs(25) && wait <= Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SharedChatMode {
+    #[default]
+    OwnChannel,
+    AllChannels,
+    AllowList,
+}
+
+impl SharedChatMode {
+    pub const ALL: [SharedChatMode; 3] = [
+        SharedChatMode::OwnChannel,
+        SharedChatMode::AllChannels,
+        SharedChatMode::AllowList,
+    ];
+}
+
+impl Display for SharedChatMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SharedChatMode::OwnChannel => write!(f, "Own channel only"),
+            SharedChatMode::AllChannels => write!(f, "All channels"),
+            SharedChatMode::AllowList => write!(f, "Selected channels"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedChatSettings {
+    pub mode: SharedChatMode,
+    #[serde(default)]
+    pub channels: Vec<String>,
+}
+
+impl SharedChatSettings {
+    pub fn allows(&self, source_login: &str) -> bool {
+        match self.mode {
+            SharedChatMode::OwnChannel => false,
+            SharedChatMode::AllChannels => true,
+            SharedChatMode::AllowList => self.channels.iter().any(|channel| {
+                channel
+                    .trim()
+                    .trim_start_matches('#')
+                    .eq_ignore_ascii_case(source_login)
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(mode: SharedChatMode, channels: &[&str]) -> SharedChatSettings {
+        SharedChatSettings {
+            mode,
+            channels: channels.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn own_channel_mode_ignores_shared_messages() {
+        assert!(!settings(SharedChatMode::OwnChannel, &["partner"]).allows("partner"));
+    }
+
+    #[test]
+    fn all_channels_mode_answers_everyone() {
+        assert!(settings(SharedChatMode::AllChannels, &[]).allows("partner"));
+    }
+
+    #[test]
+    fn allow_list_matches_logins_loosely() {
+        let settings = settings(SharedChatMode::AllowList, &[" #Partner "]);
+        assert!(settings.allows("partner"));
+        assert!(!settings.allows("stranger"));
+    }
+}